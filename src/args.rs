@@ -9,17 +9,21 @@ type ArgsVec<'a> = SmallVec<[&'a str; 3]>;
 
 pub(crate) struct Args<'a> {
     inner: ArgsVec<'a>,
+    positional_args_number: usize, // Args after these ones are "key=value" kwargs.
 }
 
 impl<'a> Args<'a> {
-    // TODO : create a nested enum in the not yet created 'animessage::Error' enum (in main.rs) for parsing errors and replace anyhow::Error with the corresponding variant
-    pub(crate) fn parse(string_to_parse: &'a str, args_number_expected: usize) -> ArgsResult<Self> {
-        let args = string_to_parse
+    fn split(string_to_parse: &'a str) -> ArgsVec<'a> {
+        string_to_parse
             .split('"')
-            .into_iter()
             .skip(1)
             .step_by(2)
-            .collect::<ArgsVec>();
+            .collect::<ArgsVec>()
+    }
+
+    // TODO : create a nested enum in the not yet created 'animessage::Error' enum (in main.rs) for parsing errors and replace anyhow::Error with the corresponding variant
+    pub(crate) fn parse(string_to_parse: &'a str, args_number_expected: usize) -> ArgsResult<Self> {
+        let args = Args::split(string_to_parse);
 
         let args_number_received = args.len();
         if args_number_received != args_number_expected {
@@ -29,16 +33,89 @@ impl<'a> Args<'a> {
             });
         }
 
-        Ok(Args { inner: args })
+        Ok(Args {
+            inner: args,
+            positional_args_number: args_number_received,
+        })
+    }
+
+    /// Same as `parse`, except that the last `optional_args_number` args can be omitted. Use `get_opt` to read them.
+    pub(crate) fn parse_optional(
+        string_to_parse: &'a str,
+        args_number_expected: usize,
+        optional_args_number: usize,
+    ) -> ArgsResult<Self> {
+        let args = Args::split(string_to_parse);
+
+        let args_number_received = args.len();
+        let min_args_number = args_number_expected - optional_args_number;
+        if !(min_args_number..=args_number_expected).contains(&args_number_received) {
+            return Err(ArgsError::WrongArgsAmount {
+                received: args_number_received,
+                expected: args_number_expected,
+            });
+        }
+
+        Ok(Args {
+            inner: args,
+            positional_args_number: args_number_received,
+        })
+    }
+
+    /// Parses `args_number_expected` positional args followed by any number of optional "key=value" args, whose keys must be in `kwargs_allowed`. Use `kwarg` to read them.
+    pub(crate) fn parse_kwargs(
+        string_to_parse: &'a str,
+        args_number_expected: usize,
+        kwargs_allowed: &[&str],
+    ) -> ArgsResult<Self> {
+        let args = Args::split(string_to_parse);
+
+        let args_number_received = args.len();
+        if args_number_received < args_number_expected {
+            return Err(ArgsError::WrongArgsAmount {
+                received: args_number_received,
+                expected: args_number_expected,
+            });
+        }
+
+        let args = Args {
+            inner: args,
+            positional_args_number: args_number_expected,
+        };
+
+        for kwarg in args.kwargs() {
+            match kwarg.split_once('=') {
+                Some((key, _value)) if kwargs_allowed.contains(&key) => (),
+                Some((key, _value)) => {
+                    return Err(ArgsError::UnknownKwarg {
+                        key: key.to_string(),
+                        allowed: kwargs_allowed.join(", "),
+                    })
+                }
+                None => {
+                    return Err(ArgsError::NotAKwarg {
+                        arg: kwarg.to_string(),
+                    })
+                }
+            }
+        }
+
+        Ok(args)
     }
 
-    // pub(crate) fn kwargs(&self, from_index: usize) -> ArgsResult<&[&'a str]> {
-    //     let max_index = self.inner.len() - 1;
-    //     if from_index > max_index {
-    //         return Err(ArgsError::MissingArgs { index: from_index, max_index })
-    //     }
-    //     Ok(&self.inner[from_index..])
-    // }
+    pub(crate) fn kwargs(&self) -> &[&'a str] {
+        &self.inner[self.positional_args_number..]
+    }
+
+    /// Value of the "key=value" arg with this key, if it has been given.
+    pub(crate) fn kwarg(&self, key: &str) -> Option<&'a str> {
+        self.kwargs()
+            .iter()
+            .find_map(|kwarg| match kwarg.split_once('=') {
+                Some((k, value)) if k == key => Some(value),
+                _ => None,
+            })
+    }
 
     pub(crate) fn get(&self, index: usize) -> &str {
         // let inner_len = &self.inner.len();
@@ -47,6 +124,10 @@ impl<'a> Args<'a> {
         // }
         self.as_ref()[index]
     }
+
+    pub(crate) fn get_opt(&self, index: usize) -> Option<&str> {
+        self.as_ref().get(index).copied()
+    }
 }
 
 impl<'a> AsRef<ArgsVec<'a>> for Args<'a> {
@@ -67,16 +148,76 @@ pub(crate) enum ArgsError {
     WrongArgsAmount { received: usize, expected: usize }, // (number of args received, number of args expected)
     #[error("check your function call for missing or misordered args. arg index {index:?} is out of bounds (max index : {max_index:?}). ")]
     MissingArgs { index: usize, max_index: usize },
+    #[error("unknown optional arg {key:?}. Optional args of this function are : {allowed}.")]
+    UnknownKwarg { key: String, allowed: String },
+    #[error("{arg:?} isn't an optional arg. Optional args must be written as \"key=value\", after the other args.")]
+    NotAKwarg { arg: String },
 }
 
 pub(crate) type ArgsResult<T> = Result<T, ArgsError>;
 
 pub(crate) fn duration_from_arg(duration: &str) -> anyhow::Result<Duration> {
-    //
-    match duration.parse::<f64>() {
-        Ok(f) => return Ok(Duration::from_secs_f64(f)),
-        Err(_) => {
-            anyhow::bail!("Can't convert the 1st argument into a decimal. Make sure your number is written as a decimal and not an integer. Example : Write 1.0 instead of 1.");
+    // Seconds by default. "ms" and "s" suffixes are allowed : "1.5", "1.5s" and "1500ms" are the same duration.
+    let (number, to_secs) = if let Some(ms) = duration.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = duration.strip_suffix('s') {
+        (s, 1.0)
+    } else {
+        (duration, 1.0)
+    };
+    match number.trim().parse::<f64>() {
+        Ok(f) if f >= 0.0 => Ok(Duration::from_secs_f64(f * to_secs)),
+        _ => {
+            anyhow::bail!("Can't convert {:?} into a duration. Write a positive decimal number of seconds, optionally followed by \"s\" or \"ms\". Examples : 1.0, 1.5s, 200ms.", duration);
         }
     }
 }
+
+pub(crate) fn position_from_arg(position: &str) -> anyhow::Result<(i32, i32)> {
+    // "column,row", for example "0,10".
+    let parsed = position
+        .split_once(',')
+        .and_then(|(column, row)| Some((column.trim().parse().ok()?, row.trim().parse().ok()?)));
+    match parsed {
+        Some(position) => Ok(position),
+        None => anyhow::bail!(
+            "Can't convert {:?} into a position. Write it as \"column,row\", for example \"0,10\".",
+            position
+        ),
+    }
+}
+
+pub(crate) fn size_from_arg(size: &str) -> anyhow::Result<u32> {
+    match size.parse::<u32>() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => anyhow::bail!(
+            "Can't convert {:?} into a size. Write a number of columns or rows greater than 0.",
+            size
+        ),
+    }
+}
+
+pub(crate) fn speed_from_arg(speed: &str) -> anyhow::Result<f64> {
+    match speed.trim().parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => anyhow::bail!(
+            "Can't convert {:?} into a speed. Write a decimal number greater than 0, for example 2.0 to play twice as fast.",
+            speed
+        ),
+    }
+}
+
+pub(crate) fn volume_from_arg(volume: &str) -> anyhow::Result<f32> {
+    // A multiplier of the file's volume, or a percentage : "0.5" and "50%" are the same volume.
+    let parsed = match volume.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().map(|percent| percent / 100.0),
+        None => volume.trim().parse::<f32>(),
+    };
+    match parsed {
+        Ok(volume) if volume >= 0.0 && volume.is_finite() => Ok(volume),
+        _ => anyhow::bail!(
+            "Can't convert {:?} into a volume. Write a positive decimal number (1.0 is the file's volume) or a percentage, for example 0.5 or 50%.",
+            volume
+        ),
+    }
+}
@@ -0,0 +1,459 @@
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+
+use super::*;
+
+const AUDIO_POLL_INTERVAL: Duration = Duration::from_millis(10);
+const TYPEWRITER_MIN_INTERVAL: Duration = Duration::from_millis(35); // Faster typing plays fewer sounds instead of flooding the mixer.
+const TICK: Tone = Tone {
+    frequency: Some(1800.0),
+    duration: Duration::from_millis(12),
+};
+
+pub(crate) struct AudioOptions {
+    pub(crate) channel: Option<String>, // Sounds without a channel can't be controlled once they're playing.
+    pub(crate) looped: bool,
+    pub(crate) start: Duration,
+    pub(crate) volume: f32,
+}
+
+impl Default for AudioOptions {
+    fn default() -> Self {
+        AudioOptions {
+            channel: None,
+            looped: false,
+            start: Duration::ZERO,
+            volume: 1.0,
+        }
+    }
+}
+
+/// Sound played for each character typed by --[PRINT]-- and --[PRINT_LINE]--.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TypewriterSound {
+    Tick, // Synthesized, so no file is needed.
+    File(PathBuf),
+}
+
+impl TypewriterSound {
+    /// "tick", a path to an audio file, or "none" for no sound.
+    pub(crate) fn from_arg(sound: &str) -> Option<Self> {
+        match sound {
+            "none" => None,
+            "tick" => Some(TypewriterSound::Tick),
+            path => Some(TypewriterSound::File(path.into())),
+        }
+    }
+}
+
+/// Where sounds are played. Each named channel plays one sound at a time.
+pub(crate) trait AudioBackend {
+    /// Plays an audio file in the background, replacing the sound of its channel.
+    fn play(&mut self, path: &Path, options: &AudioOptions) -> AnyResult<()>;
+    /// Same as `play` with synthesized tones. `options.start` is ignored.
+    fn play_tones(&mut self, tones: Tones, options: &AudioOptions) -> AnyResult<()>;
+    /// Stops a channel, or every channel if `channel` is `None`. Same for the other functions.
+    fn stop(&mut self, channel: Option<&str>) -> AnyResult<()>;
+    fn pause(&mut self, channel: Option<&str>) -> AnyResult<()>;
+    fn resume(&mut self, channel: Option<&str>) -> AnyResult<()>;
+    fn set_volume(&mut self, channel: Option<&str>, volume: f32) -> AnyResult<()>;
+    /// Whether a channel still has sound to play. Looped sounds never end, and unknown channels have ended.
+    fn is_playing(&self, channel: &str) -> bool;
+}
+
+/// Audio of an animessage, sounds and speech. The output device is only opened when the first sound is played, so animessages without sounds work without one.
+pub struct Audio {
+    enabled: bool,
+    backend: Option<Box<dyn AudioBackend>>,
+    speech: Option<Box<dyn SpeechEngine>>,
+    speak_steps: bool,
+    typewriter: Option<TypewriterSound>,
+    last_typewriter_sound: Option<Duration>, // Time of the stage.
+}
+
+impl Audio {
+    /// Sounds are ignored and speech is only logged if `enabled` is false.
+    pub fn new(enabled: bool) -> Self {
+        Audio {
+            enabled,
+            backend: None,
+            speech: None,
+            speak_steps: false,
+            typewriter: None,
+            last_typewriter_sound: None,
+        }
+    }
+
+    /// Reads every printed step aloud.
+    pub fn with_speak_steps(mut self, enabled: bool) -> Self {
+        self.speak_steps = enabled;
+        self
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_backend(mut self, backend: Box<dyn AudioBackend>) -> Self {
+        self.enabled = true;
+        self.backend = Some(backend);
+        self
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_speech(mut self, speech: Box<dyn SpeechEngine>) -> Self {
+        self.speech = Some(speech);
+        self
+    }
+
+    /// Waits until a channel has ended, animating the stage in the meantime. Doesn't wait if the stage skips delays or is interrupted.
+    pub(crate) fn wait_for_channel(&mut self, channel: &str, stage: &mut Stage) {
+        while stage.waits() && !stage.is_interrupted() && self.backend().is_playing(channel) {
+            stage.sleep(AUDIO_POLL_INTERVAL);
+        }
+    }
+
+    /// Sound of the characters typed by the next prints which don't have their own "sound" arg.
+    pub(crate) fn set_typewriter(&mut self, sound: Option<TypewriterSound>) {
+        self.typewriter = sound;
+    }
+
+    pub(crate) fn typewriter(&self) -> Option<&TypewriterSound> {
+        self.typewriter.as_ref()
+    }
+
+    /// Plays `sound` in the background for a character typed at `now`, unless a sound has been played very recently.
+    pub(crate) fn type_character(
+        &mut self,
+        sound: &TypewriterSound,
+        now: Duration,
+    ) -> AnyResult<()> {
+        if self
+            .last_typewriter_sound
+            .is_some_and(|last| now - last < TYPEWRITER_MIN_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_typewriter_sound = Some(now);
+
+        let options = AudioOptions::default();
+        match sound {
+            TypewriterSound::Tick => self
+                .backend()
+                .play_tones(Tones::new(vec![TICK], Wave::Square, false), &options),
+            TypewriterSound::File(path) => self.backend().play(path, &options),
+        }
+    }
+
+    pub(crate) fn speaks_steps(&self) -> bool {
+        self.speak_steps
+    }
+
+    pub(crate) fn speech(&mut self) -> &mut dyn SpeechEngine {
+        let enabled = self.enabled;
+        self.speech
+            .get_or_insert_with(|| {
+                if !enabled {
+                    return Box::new(LogSpeech);
+                }
+                match ProgramSpeech::find() {
+                    Some(speech) => Box::new(speech),
+                    None => {
+                        warn!("No text-to-speech program has been found, so text will be logged instead of read aloud. Install espeak-ng to hear it.");
+                        Box::new(LogSpeech)
+                    }
+                }
+            })
+            .as_mut()
+    }
+
+    pub(crate) fn backend(&mut self) -> &mut dyn AudioBackend {
+        let enabled = self.enabled;
+        self.backend
+            .get_or_insert_with(|| {
+                if !enabled {
+                    return Box::new(NullAudio);
+                }
+                match RodioAudio::try_default() {
+                    Ok(audio) => Box::new(audio),
+                    Err(err) => {
+                        warn!("Can't open your default audio output device, so sounds won't be played. Use --no-audio to hide this warning. Error details : {}", err);
+                        Box::new(NullAudio)
+                    }
+                }
+            })
+            .as_mut()
+    }
+}
+
+/// Sounds played with the default output device.
+struct RodioAudio {
+    _stream: OutputStream, // Sounds stop when it's dropped.
+    stream_handle: OutputStreamHandle,
+    channels: HashMap<String, Sink>,
+}
+
+impl RodioAudio {
+    fn try_default() -> AnyResult<Self> {
+        let (stream, stream_handle) = OutputStream::try_default()?;
+        Ok(RodioAudio {
+            _stream: stream,
+            stream_handle,
+            channels: HashMap::new(),
+        })
+    }
+
+    fn add_to_channel(&mut self, sink: Sink, options: &AudioOptions) {
+        match &options.channel {
+            Some(channel) => {
+                if let Some(previous) = self.channels.insert(channel.clone(), sink) {
+                    previous.stop();
+                }
+            }
+            None => sink.detach(),
+        }
+    }
+
+    fn sinks(&self, channel: Option<&str>) -> AnyResult<Vec<&Sink>> {
+        match channel {
+            Some(channel) => Ok(vec![self
+                .channels
+                .get(channel)
+                .ok_or_else(|| unknown_channel(channel))?]),
+            None => Ok(self.channels.values().collect()),
+        }
+    }
+}
+
+impl AudioBackend for RodioAudio {
+    fn play(&mut self, path: &Path, options: &AudioOptions) -> AnyResult<()> {
+        let sink = Sink::try_new(&self.stream_handle)?;
+        sink.set_volume(options.volume);
+        sink.append(decode(path)?.skip_duration(options.start));
+        if options.looped {
+            // The next loops start from the beginning of the file, not from `start`.
+            sink.append(decode(path)?.buffered().repeat_infinite());
+        }
+        self.add_to_channel(sink, options);
+        Ok(())
+    }
+
+    fn play_tones(&mut self, tones: Tones, options: &AudioOptions) -> AnyResult<()> {
+        let sink = Sink::try_new(&self.stream_handle)?;
+        sink.set_volume(options.volume);
+        sink.append(tones);
+        self.add_to_channel(sink, options);
+        Ok(())
+    }
+
+    fn stop(&mut self, channel: Option<&str>) -> AnyResult<()> {
+        match channel {
+            Some(channel) => self
+                .channels
+                .remove(channel)
+                .ok_or_else(|| unknown_channel(channel))?
+                .stop(),
+            None => self.channels.drain().for_each(|(_name, sink)| sink.stop()),
+        }
+        Ok(())
+    }
+
+    fn pause(&mut self, channel: Option<&str>) -> AnyResult<()> {
+        self.sinks(channel)?.iter().for_each(|sink| sink.pause());
+        Ok(())
+    }
+
+    fn resume(&mut self, channel: Option<&str>) -> AnyResult<()> {
+        self.sinks(channel)?.iter().for_each(|sink| sink.play());
+        Ok(())
+    }
+
+    fn set_volume(&mut self, channel: Option<&str>, volume: f32) -> AnyResult<()> {
+        self.sinks(channel)?
+            .iter()
+            .for_each(|sink| sink.set_volume(volume));
+        Ok(())
+    }
+
+    fn is_playing(&self, channel: &str) -> bool {
+        self.channels.get(channel).is_some_and(|sink| !sink.empty())
+    }
+}
+
+/// Ignores sounds, when audio is disabled or there's no output device.
+struct NullAudio;
+
+impl AudioBackend for NullAudio {
+    fn play(&mut self, _path: &Path, _options: &AudioOptions) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn play_tones(&mut self, _tones: Tones, _options: &AudioOptions) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn stop(&mut self, _channel: Option<&str>) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn pause(&mut self, _channel: Option<&str>) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn resume(&mut self, _channel: Option<&str>) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn set_volume(&mut self, _channel: Option<&str>, _volume: f32) -> AnyResult<()> {
+        Ok(())
+    }
+
+    fn is_playing(&self, _channel: &str) -> bool {
+        false
+    }
+}
+
+/// Length of an audio file. Files which don't store it are decoded entirely to measure it.
+pub(crate) fn audio_duration(path: &Path) -> AnyResult<Duration> {
+    let source = decode(path)?;
+    if let Some(duration) = source.total_duration() {
+        return Ok(duration);
+    }
+    let samples_per_second = source.sample_rate() as f64 * source.channels() as f64;
+    let samples_n = source.count();
+    Ok(Duration::from_secs_f64(
+        samples_n as f64 / samples_per_second,
+    ))
+}
+
+fn decode(path: &Path) -> AnyResult<rodio::Decoder<BufReader<File>>> {
+    let file = File::open(path)?;
+    Ok(rodio::Decoder::new(BufReader::new(file))?)
+}
+
+fn unknown_channel(channel: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "There is no audio channel named {:?}. Play a sound in it first with --[AUDIO]-- \"path\" \"channel={}\".",
+        channel,
+        channel
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    pub(crate) struct PlayedSound {
+        pub(crate) path: PathBuf,
+        pub(crate) channel: Option<String>,
+        pub(crate) at: Duration, // Time of the backend's clock.
+    }
+
+    /// Simulated time shared between a stage and a backend, so that sounds are recorded at the time they're played on stage.
+    #[derive(Clone, Default)]
+    pub(crate) struct SharedClock(Rc<Cell<Duration>>);
+
+    impl Clock for SharedClock {
+        fn now(&self) -> Duration {
+            self.0.get()
+        }
+
+        fn sleep(&mut self, duration: Duration) {
+            self.0.set(self.0.get() + duration);
+        }
+
+        fn waits(&self) -> bool {
+            false
+        }
+    }
+
+    /// Records the sounds that have been played instead of playing them.
+    pub(crate) struct RecordingAudio {
+        clock: SharedClock,
+        played: Rc<RefCell<Vec<PlayedSound>>>,
+    }
+
+    impl RecordingAudio {
+        pub(crate) fn new() -> (Self, Rc<RefCell<Vec<PlayedSound>>>) {
+            let played = Rc::new(RefCell::new(Vec::new()));
+            let audio = RecordingAudio {
+                clock: SharedClock::default(),
+                played: Rc::clone(&played),
+            };
+            (audio, played)
+        }
+
+        /// Clock to give the stage, to record sounds at its time.
+        pub(crate) fn clock(&self) -> SharedClock {
+            self.clock.clone()
+        }
+    }
+
+    impl AudioBackend for RecordingAudio {
+        fn play(&mut self, path: &Path, options: &AudioOptions) -> AnyResult<()> {
+            self.played.borrow_mut().push(PlayedSound {
+                path: path.to_path_buf(),
+                channel: options.channel.clone(),
+                at: self.clock.now(),
+            });
+            Ok(())
+        }
+
+        fn play_tones(&mut self, _tones: Tones, _options: &AudioOptions) -> AnyResult<()> {
+            Ok(())
+        }
+
+        fn stop(&mut self, _channel: Option<&str>) -> AnyResult<()> {
+            Ok(())
+        }
+
+        fn pause(&mut self, _channel: Option<&str>) -> AnyResult<()> {
+            Ok(())
+        }
+
+        fn resume(&mut self, _channel: Option<&str>) -> AnyResult<()> {
+            Ok(())
+        }
+
+        fn set_volume(&mut self, _channel: Option<&str>, _volume: f32) -> AnyResult<()> {
+            Ok(())
+        }
+
+        fn is_playing(&self, _channel: &str) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn sounds_are_recorded_in_order() {
+        let (backend, played) = RecordingAudio::new();
+        let mut stage = Stage::new().with_headless(true).with_clock(backend.clock());
+        let mut audio = Audio::new(true).with_backend(Box::new(backend));
+        let animessage = "--[AUDIO]-- \"intro.mp3\"\n--[WAIT]-- \"50ms\"\n--[AUDIO]-- \"music.mp3\" \"channel=music\"";
+        display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio).unwrap();
+
+        let played = played.borrow();
+        assert_eq!(played.len(), 2);
+        assert_eq!(played[0].path, PathBuf::from("intro.mp3"));
+        assert_eq!(played[1].channel.as_deref(), Some("music"));
+        assert_eq!(played[1].at - played[0].at, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn typewriter_sounds_are_rate_limited() {
+        let (backend, played) = RecordingAudio::new();
+        let mut audio = Audio::new(true).with_backend(Box::new(backend));
+        let mut stage = Stage::new().with_headless(true);
+        let animessage = "--[TYPEWRITER]-- \"click.wav\"\nab cd\n--[PRINT]-- \"20ms\"\nefghijkl\n--[PRINT]-- \"1ms\" \"sound=none\"";
+        display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio).unwrap();
+
+        // Letters are typed faster than the minimum interval, so some of them are silent. The second print has no sound at all.
+        let played = played.borrow();
+        assert!((1..4).contains(&played.len()));
+        assert!(played
+            .iter()
+            .all(|sound| sound.path == Path::new("click.wav")));
+    }
+}
@@ -0,0 +1,122 @@
+use std::time::Instant;
+
+use super::*;
+
+/// Time of the stage, which every delay of an animessage is waited on.
+pub trait Clock {
+    /// Time since the clock has been created.
+    fn now(&self) -> Duration;
+
+    /// Waits until `duration` has passed on this clock.
+    fn sleep(&mut self, duration: Duration);
+
+    /// Whether sleeping takes some real time.
+    fn waits(&self) -> bool {
+        true
+    }
+}
+
+/// Wall-clock time.
+pub struct RealClock {
+    start: Instant,
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        RealClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        RealClock::new()
+    }
+}
+
+impl Clock for RealClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        sleep(duration)
+    }
+}
+
+/// Simulated time : sleeping only moves the clock forward, and returns right away.
+#[derive(Default)]
+pub struct VirtualClock {
+    time: Duration,
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.time
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        self.time += duration;
+    }
+
+    fn waits(&self) -> bool {
+        false
+    }
+}
+
+/// Wall-clock time going `speed` times faster. Its time is the animessage's, so a 2s delay still lasts 2s on this clock.
+pub struct ScaledClock {
+    start: Instant,
+    speed: f64,
+}
+
+impl ScaledClock {
+    pub fn new(speed: f64) -> Self {
+        ScaledClock {
+            start: Instant::now(),
+            speed,
+        }
+    }
+}
+
+impl Clock for ScaledClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed().mul_f64(self.speed)
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        sleep(duration.div_f64(self.speed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn virtual_and_scaled_clocks() {
+        let mut clock = VirtualClock::default();
+        clock.sleep(Duration::from_secs(60));
+        clock.sleep(Duration::from_millis(500));
+        assert_eq!(clock.now(), Duration::from_millis(60_500));
+
+        let mut clock = ScaledClock::new(100.0);
+        let started = Instant::now();
+        clock.sleep(Duration::from_secs(1));
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert!(clock.now() >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn speed_scales_the_delays_of_animessages() {
+        let mut stage = Stage::new().with_clock(ScaledClock::new(20.0));
+        let mut audio = Audio::new(false);
+        let started = Instant::now();
+        let animessage = "Hi\n--[PRINT]-- \"100ms\"\n--[WAIT]-- \"2\"";
+        display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio).unwrap();
+
+        assert!(started.elapsed() < Duration::from_millis(1500));
+        assert!(stage.now() >= Duration::from_millis(2200));
+    }
+}
@@ -0,0 +1,180 @@
+use console_engine::{
+    crossterm::{cursor as ce_cursor, queue, style},
+    pixel::{self, Pixel},
+    screen::Screen,
+    Color,
+};
+
+use super::*;
+
+const UNKNOWN_CELL: char = '\u{0}'; // Never printed, so the first flush redraws every cell.
+
+/// Double-buffered cell grid. The animessage draws into `base`, and `flush` only writes the cells that differ from what's already on the terminal.
+pub(crate) struct Compositor {
+    base: Screen,
+    front: Screen,
+    cursor: (i32, i32), // (column, row)
+}
+
+impl Compositor {
+    pub(crate) fn new(columns: u32, rows: u32) -> Self {
+        Compositor {
+            base: Screen::new(columns, rows),
+            front: Screen::new_fill(columns, rows, pixel::pxl(UNKNOWN_CELL)),
+            cursor: (0, 0),
+        }
+    }
+
+    /// Writes text at the cursor position, moving the cursor like a terminal would : wrapping at the right border and scrolling at the bottom.
+    pub(crate) fn print(&mut self, text: &str) {
+        for c in text.chars() {
+            self.put(c, Color::Reset, Color::Reset);
+        }
+    }
+
+    /// Same as `print`, with the colors of each cell.
+    pub(crate) fn print_cells(&mut self, cells: &[Cell]) {
+        for cell in cells {
+            self.put(cell.chr, to_color(cell.fg), to_color(cell.bg));
+        }
+    }
+
+    fn put(&mut self, c: char, fg: Color, bg: Color) {
+        let (width, height) = (self.base.get_width() as i32, self.base.get_height() as i32);
+        match c {
+            '\n' => {
+                self.cursor.0 = 0;
+                self.cursor.1 += 1;
+            }
+            '\r' => self.cursor.0 = 0,
+            _ => {
+                if self.cursor.0 >= width {
+                    self.cursor.0 = 0;
+                    self.cursor.1 += 1;
+                }
+                if self.cursor.1 >= height {
+                    self.scroll_up();
+                }
+                let c = if c == '\t' { ' ' } else { c };
+                self.base
+                    .set_pxl(self.cursor.0, self.cursor.1, pixel::pxl_fbg(c, fg, bg));
+                self.cursor.0 += 1;
+            }
+        }
+        if self.cursor.1 >= height {
+            self.scroll_up();
+        }
+    }
+
+    /// Writes text from the start of `row`, without moving the cursor.
+    pub(crate) fn print_row(&mut self, row: usize, text: &str) {
+        let cursor = self.cursor;
+        self.cursor = (0, row as i32);
+        self.print(text);
+        self.cursor = cursor;
+    }
+
+    pub(crate) fn move_cursor(&mut self, columns: usize, rows: usize) {
+        self.cursor = (columns as i32, rows as i32);
+    }
+
+    pub(crate) fn move_cursor_up(&mut self, rows: usize) {
+        self.cursor.1 = (self.cursor.1 - rows as i32).max(0);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.base.clear();
+        self.cursor = (0, 0);
+    }
+
+    pub(crate) fn resize(&mut self, columns: u32, rows: u32) {
+        self.base.resize(columns, rows);
+        self.front = Screen::new_fill(columns, rows, pixel::pxl(UNKNOWN_CELL));
+    }
+
+    fn scroll_up(&mut self) {
+        self.base.scroll(0, 1, pixel::pxl(' '));
+        self.cursor.1 -= 1;
+    }
+
+    /// Composes `overlays` (position, cells) on top of what has been printed, writes the cells that changed since the last flush, then puts the terminal cursor where the animessage expects it.
+    /// Spaces in overlays are transparent.
+    pub(crate) fn flush(
+        &mut self,
+        out: &mut impl Write,
+        overlays: &[(Position, &Screen)],
+    ) -> io::Result<()> {
+        let mut frame = self.base.clone();
+        for &((x, y), overlay) in overlays {
+            frame.print_screen_alpha(x, y, overlay, ' ');
+        }
+        let mut buf: Vec<u8> = Vec::new();
+        let mut pen: Option<(Color, Color)> = None;
+        let (width, height) = (frame.get_width() as i32, frame.get_height() as i32);
+
+        for y in 0..height {
+            let mut next_x = None; // Column where the terminal cursor is after the last printed cell of this row.
+            for x in 0..width {
+                let new_pxl: Pixel = frame.get_pxl(x, y).unwrap(); // Can't be out of bounds.
+                if self.front.get_pxl(x, y).ok() == Some(new_pxl) {
+                    continue;
+                }
+
+                if next_x != Some(x) {
+                    queue!(buf, ce_cursor::MoveTo(x as u16, y as u16))?;
+                }
+                if pen != Some(new_pxl.get_colors()) {
+                    queue!(
+                        buf,
+                        style::SetForegroundColor(new_pxl.fg),
+                        style::SetBackgroundColor(new_pxl.bg)
+                    )?;
+                    pen = Some(new_pxl.get_colors());
+                }
+                queue!(buf, style::Print(new_pxl.chr))?;
+                next_x = Some(x + 1);
+            }
+        }
+
+        if pen.is_some() {
+            queue!(buf, style::ResetColor)?;
+        }
+        let cursor_x = self.cursor.0.clamp(0, width - 1);
+        let cursor_y = self.cursor.1.clamp(0, height - 1);
+        queue!(buf, ce_cursor::MoveTo(cursor_x as u16, cursor_y as u16))?;
+
+        self.front = frame;
+        out.write_all(&buf)?;
+        out.flush()
+    }
+}
+
+fn to_color(color: CellColor) -> Color {
+    match color {
+        CellColor::Default => Color::Reset,
+        CellColor::Rgb(r, g, b) => Color::Rgb { r, g, b },
+        CellColor::Indexed(n) => Color::AnsiValue(n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flush_only_writes_changed_cells() {
+        let mut compositor = Compositor::new(10, 3);
+        let mut out = Vec::new();
+        compositor.print("abc");
+        compositor.flush(&mut out, &[]).unwrap();
+
+        out.clear();
+        compositor.move_cursor(1, 0);
+        compositor.print("X");
+        compositor.flush(&mut out, &[]).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains('X'));
+        assert!(!out.contains('a') && !out.contains('c'));
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    time::Instant,
+};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+
+use super::*;
+
+const KEYS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const SUSPEND_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// What the viewer asked for with a key while the animessage plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Control {
+    /// Space : pauses, or resumes if paused.
+    Pause,
+    /// Enter : shows the rest of the current step right away.
+    FinishStep,
+    /// Left : goes back to the previous step.
+    PreviousStep,
+    /// Right : goes forward again to the step gone back from, or else finishes the current step.
+    NextStep,
+    /// N : skips to the next marker.
+    NextMarker,
+    /// Q, Escape or Ctrl + C : stops the animessage.
+    Quit,
+}
+
+impl Control {
+    pub(crate) fn from_key(key: KeyEvent) -> Option<Self> {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Control::Quit)
+            }
+            KeyCode::Char(' ') => Some(Control::Pause),
+            KeyCode::Enter => Some(Control::FinishStep),
+            KeyCode::Left => Some(Control::PreviousStep),
+            KeyCode::Right => Some(Control::NextStep),
+            KeyCode::Char('n') | KeyCode::Char('N') => Some(Control::NextMarker),
+            KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => Some(Control::Quit),
+            _ => None,
+        }
+    }
+
+    /// A key doing this.
+    #[cfg(test)]
+    pub(crate) fn key(self) -> KeyEvent {
+        let code = match self {
+            Control::Pause => KeyCode::Char(' '),
+            Control::FinishStep => KeyCode::Enter,
+            Control::PreviousStep => KeyCode::Left,
+            Control::NextStep => KeyCode::Right,
+            Control::NextMarker => KeyCode::Char('n'),
+            Control::Quit => KeyCode::Char('q'),
+        };
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+}
+
+// Flags shared with the thread reading the keys.
+#[derive(Default)]
+struct KeysState {
+    stopped: AtomicBool,
+    suspended: AtomicBool,
+    idle: AtomicBool, // The thread has seen that it's suspended, and doesn't read keys anymore.
+}
+
+/// Keys read in the background with the terminal in raw mode, which is restored when they're dropped.
+/// They're the playback controls, and the keys bound to markers by --[BIND]-- .
+pub struct Controls {
+    receiver: Receiver<KeyEvent>,
+    state: Arc<KeysState>,
+    raw_mode: bool,
+}
+
+impl Controls {
+    /// Puts the terminal in raw mode and starts reading the keys.
+    pub fn start() -> AnyResult<Self> {
+        terminal::enable_raw_mode()?;
+        let (sender, receiver) = mpsc::channel();
+        let state = Arc::new(KeysState::default());
+        let thread_state = state.clone();
+        thread::spawn(move || read_keys(sender, &thread_state));
+        Ok(Controls {
+            receiver,
+            state,
+            raw_mode: true,
+        })
+    }
+
+    /// Controls which only receive the keys sent to `receiver`, without touching the terminal.
+    #[cfg(test)]
+    pub(crate) fn from_receiver(receiver: Receiver<KeyEvent>) -> Self {
+        Controls {
+            receiver,
+            state: Arc::default(),
+            raw_mode: false,
+        }
+    }
+
+    pub(crate) fn try_recv(&self) -> Option<KeyEvent> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Stops reading the keys and leaves raw mode, so that something else such as a prompt can read them and Ctrl + C works again.
+    pub(crate) fn suspend(&self) {
+        self.state.idle.store(false, Ordering::SeqCst);
+        self.state.suspended.store(true, Ordering::SeqCst);
+        if !self.raw_mode {
+            return;
+        }
+        let start = Instant::now();
+        while !self.state.idle.load(Ordering::SeqCst) && start.elapsed() < SUSPEND_TIMEOUT {
+            sleep(Duration::from_millis(5));
+        }
+        let _ = terminal::disable_raw_mode();
+    }
+
+    /// Reads the keys again in raw mode, ignoring those pressed while suspended.
+    pub(crate) fn resume(&self) {
+        if self.raw_mode {
+            let _ = terminal::enable_raw_mode();
+        }
+        self.state.suspended.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Controls {
+    fn drop(&mut self) {
+        self.state.stopped.store(true, Ordering::SeqCst);
+        if self.raw_mode {
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+fn read_keys(sender: Sender<KeyEvent>, state: &KeysState) {
+    let mut was_suspended = false;
+    while !state.stopped.load(Ordering::SeqCst) {
+        if state.suspended.load(Ordering::SeqCst) {
+            state.idle.store(true, Ordering::SeqCst);
+            was_suspended = true;
+            sleep(KEYS_POLL_INTERVAL);
+            continue;
+        }
+        if was_suspended {
+            while let Ok(true) = event::poll(Duration::ZERO) {
+                let _ = event::read(); // Keys meant for what suspended the controls.
+            }
+            was_suspended = false;
+        }
+        match event::poll(KEYS_POLL_INTERVAL) {
+            Ok(true) => {
+                if let Ok(Event::Key(key)) = event::read() {
+                    if sender.send(key).is_err() {
+                        return;
+                    }
+                }
+            }
+            Ok(false) => (),
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play_with_controls(animessage: &str, controls: &[Control]) -> (MemoryRenderer, Duration) {
+        let keys: Vec<KeyEvent> = controls.iter().map(|control| control.key()).collect();
+        play_with_keys(animessage, &keys)
+    }
+
+    fn play_with_keys(animessage: &str, keys: &[KeyEvent]) -> (MemoryRenderer, Duration) {
+        let (sender, receiver) = mpsc::channel();
+        for &key in keys {
+            sender.send(key).unwrap();
+        }
+        let screen = MemoryRenderer::new(80, 24);
+        let mut stage = Stage::new()
+            .with_renderer(screen.clone())
+            .with_controls(Controls::from_receiver(receiver));
+        let mut audio = Audio::new(false);
+        let started = Instant::now();
+        display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio).unwrap();
+        (screen, started.elapsed())
+    }
+
+    #[test]
+    fn steps_can_be_finished_skipped_and_quit() {
+        let (screen, elapsed) =
+            play_with_controls("Hello world\n--[PRINT]-- \"1\"", &[Control::FinishStep]);
+        assert!(elapsed < Duration::from_millis(500));
+        assert_eq!(screen.screen().text().trim_end(), "Hello world");
+
+        // Keys are handled while waiting, here during the first wait.
+        let animessage = "Shown\n--[PRINT]-- \"0\"\n--[WAIT]-- \"10\"\nSkipped\n--[PRINT]-- \"0\"\n--[MARKER]-- \"next\"\nShown too\n--[PRINT]-- \"0\"";
+        let (screen, elapsed) = play_with_controls(animessage, &[Control::NextMarker]);
+        assert!(elapsed < Duration::from_millis(500));
+        assert_eq!(screen.screen().text().trim_end(), "Shown\nShown too");
+
+        let (screen, elapsed) = play_with_controls(
+            "Quit\n--[PRINT]-- \"1\"\nNever shown\n--[PRINT]-- \"0\"",
+            &[Control::Quit],
+        );
+        assert!(elapsed < Duration::from_millis(500));
+        assert_eq!(screen.screen().text().trim_end(), "Q");
+    }
+
+    #[test]
+    fn bound_keys_go_to_their_marker_until_unbound() {
+        let h = KeyEvent::new(KeyCode::Char('h'), KeyModifiers::NONE);
+        let animessage = "--[BIND]-- \"h\" \"help\"\nShown\n--[PRINT]-- \"0\"\n--[WAIT]-- \"10\"\nSkipped\n--[PRINT]-- \"0\"\n--[MARKER]-- \"help\"\nHelp\n--[PRINT]-- \"0\"";
+        let (screen, elapsed) = play_with_keys(animessage, &[h]);
+        assert!(elapsed < Duration::from_millis(500));
+        assert_eq!(screen.screen().text().trim_end(), "Shown\nHelp");
+
+        let animessage = "--[BIND]-- \"h\" \"help\"\n--[UNBIND]-- \"h\"\n--[WAIT]-- \"0.2\"\nShown\n--[PRINT]-- \"0\"\n--[MARKER]-- \"help\"\nHelp\n--[PRINT]-- \"0\"";
+        let (screen, elapsed) = play_with_keys(animessage, &[h]);
+        assert!(elapsed >= Duration::from_millis(200));
+        assert_eq!(screen.screen().text().trim_end(), "Shown\nHelp");
+    }
+
+    #[test]
+    fn pausing_holds_the_stage_clock() {
+        let (sender, receiver) = mpsc::channel();
+        let mut stage = Stage::new().with_controls(Controls::from_receiver(receiver));
+        sender.send(Control::Pause.key()).unwrap();
+        let resume = thread::spawn(move || {
+            sleep(Duration::from_millis(300));
+            sender.send(Control::Pause.key()).unwrap();
+        });
+        let started = Instant::now();
+        stage.sleep(Duration::from_millis(100));
+        resume.join().unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(400));
+        assert!(stage.now() < Duration::from_millis(300));
+    }
+}
@@ -0,0 +1,521 @@
+use std::{fmt::Write as _, io::BufWriter};
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, Rgba, RgbaImage,
+};
+
+use super::*;
+
+const END_PAUSE: Duration = Duration::from_secs(2); // How long the last frame of looping exports stays before starting again.
+const GIF_SCALE: u32 = 2; // Pixels of the GIF for each pixel of the font.
+const GIF_ENCODER_SPEED: i32 = 10; // From 1 to 30, the slowest giving the best colors. Terminals don't have many.
+const SVG_CELL_SIZE: (f32, f32) = (8.4, 18.0); // (width, height) of a character in pixels, for a 14px font.
+
+/// Default colors of the exported terminal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Theme {
+    foreground: (u8, u8, u8),
+    background: (u8, u8, u8),
+}
+
+impl FromStr for Theme {
+    type Err = anyhow::Error;
+
+    fn from_str(theme: &str) -> AnyResult<Self> {
+        match theme {
+            "dark" => Ok(Theme {
+                foreground: (208, 208, 208),
+                background: (16, 16, 16),
+            }),
+            "light" => Ok(Theme {
+                foreground: (32, 32, 32),
+                background: (250, 250, 250),
+            }),
+            _ => anyhow::bail!(
+                "Unknown theme {:?}. Themes are \"dark\" and \"light\".",
+                theme
+            ),
+        }
+    }
+}
+
+impl Theme {
+    fn rgb(&self, color: CellColor, background: bool) -> (u8, u8, u8) {
+        match color {
+            CellColor::Default if background => self.background,
+            CellColor::Default => self.foreground,
+            CellColor::Indexed(n) => indexed_to_rgb(n),
+            CellColor::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+}
+
+/// What the virtual terminal showed during `duration`.
+#[derive(Clone, PartialEq)]
+struct Snapshot {
+    rows: Vec<Vec<Cell>>,
+    cursor: Option<(usize, usize)>,
+    duration: Duration,
+}
+
+/// Writes the output events of a stage to every format of `options`.
+pub(crate) fn export(events: &[(Duration, String)], options: &ExportOpts) -> AnyResult<()> {
+    if let Some(path) = &options.html {
+        export_html(events, options, path)?;
+        info!("Exported to {:?}", path);
+    }
+    if options.svg.is_some() || options.gif.is_some() {
+        let snapshots = snapshots(events, options);
+        if let Some(path) = &options.svg {
+            export_svg(&snapshots, options, path)?;
+            info!("Exported to {:?}", path);
+        }
+        if let Some(path) = &options.gif {
+            export_gif(&snapshots, options, path)?;
+            info!("Exported to {:?}", path);
+        }
+    }
+    Ok(())
+}
+
+// Samples the virtual terminal `options.fps` times per second. Identical consecutive samples are merged into one snapshot.
+fn snapshots(events: &[(Duration, String)], options: &ExportOpts) -> Vec<Snapshot> {
+    let frame_duration = Duration::from_secs_f64(1.0 / options.fps.max(1) as f64);
+    let mut vterm = VirtualTerminal::new(options.columns, options.rows);
+    let mut snapshots: Vec<Snapshot> = Vec::new();
+    let mut events = events.iter().peekable();
+    let mut time = Duration::ZERO;
+    loop {
+        while let Some((_, output)) = events.next_if(|(event_time, _)| *event_time <= time) {
+            vterm.feed(output);
+        }
+        let (rows, cursor) = (vterm.rows(), vterm.cursor());
+        match snapshots.last_mut() {
+            Some(last) if last.rows == rows && last.cursor == cursor => {
+                last.duration += frame_duration
+            }
+            _ => snapshots.push(Snapshot {
+                rows: rows.to_vec(),
+                cursor,
+                duration: frame_duration,
+            }),
+        }
+        if events.peek().is_none() {
+            break;
+        }
+        time += frame_duration;
+    }
+    if let Some(last) = snapshots.last_mut() {
+        last.duration += END_PAUSE;
+    }
+    snapshots
+}
+
+// Self-contained page replaying the frames. `__TITLE__`, `__COLUMNS__`, `__FRAMES__` and the colors are replaced when exporting.
+const HTML_PLAYER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>__TITLE__</title>
+<style>
+  body { background: #202020; color: #d0d0d0; font-family: sans-serif; margin: 2em; }
+  #screen {
+    position: relative; display: inline-block; min-width: __COLUMNS__ch; padding: 0.5em;
+    background: __BACKGROUND__; color: __FOREGROUND__;
+    font: 16px/1.2 "DejaVu Sans Mono", Menlo, Consolas, monospace;
+  }
+  #screen pre { margin: 0; height: 1.2em; white-space: pre; font: inherit; }
+  #cursor { position: absolute; width: 1ch; height: 1.2em; background: __FOREGROUND__; opacity: 0.7; }
+  #controls { margin-top: 1em; }
+  #controls button { font-size: 1em; margin-right: 0.5em; }
+</style>
+</head>
+<body>
+<div id="screen"><div id="cursor"></div></div>
+<div id="controls">
+  <button id="play">Pause</button>
+  <button id="restart">Restart</button>
+  <span id="time"></span>
+</div>
+<script>
+// Each frame is [time in seconds, [[row, html], ...], title or null, [column, row] of the cursor or null, number of rows].
+const frames = __FRAMES__;
+const duration = frames.length ? frames[frames.length - 1][0] : 0;
+const screen = document.getElementById("screen");
+const cursor = document.getElementById("cursor");
+const playButton = document.getElementById("play");
+const timeText = document.getElementById("time");
+let rows = [];
+let next = 0;
+let position = 0;
+let startedAt = null;
+
+function setRowCount(count) {
+  while (rows.length < count) {
+    const row = document.createElement("pre");
+    screen.appendChild(row);
+    rows.push(row);
+  }
+  while (rows.length > count) {
+    screen.removeChild(rows.pop());
+  }
+}
+
+function apply(frame) {
+  const [, changedRows, title, cursorPosition, rowCount] = frame;
+  setRowCount(rowCount);
+  for (const [index, html] of changedRows) {
+    rows[index].innerHTML = html;
+  }
+  if (title !== null) {
+    document.title = title;
+  }
+  cursor.style.display = cursorPosition ? "block" : "none";
+  if (cursorPosition) {
+    cursor.style.left = `calc(0.5em + ${cursorPosition[0]}ch)`;
+    cursor.style.top = `calc(0.5em + ${cursorPosition[1] * 1.2}em)`;
+  }
+}
+
+function restart() {
+  for (const row of rows) {
+    row.innerHTML = "";
+  }
+  next = 0;
+  position = 0;
+  if (startedAt !== null) {
+    startedAt = performance.now();
+  }
+  tick();
+}
+
+function tick() {
+  if (startedAt !== null) {
+    position = (performance.now() - startedAt) / 1000;
+  }
+  while (next < frames.length && frames[next][0] <= position) {
+    apply(frames[next]);
+    next++;
+  }
+  timeText.textContent = `${Math.min(position, duration).toFixed(1)} / ${duration.toFixed(1)} s`;
+  if (next >= frames.length && startedAt !== null) {
+    startedAt = null;
+    playButton.textContent = "Play";
+  }
+  if (startedAt !== null) {
+    requestAnimationFrame(tick);
+  }
+}
+
+playButton.addEventListener("click", () => {
+  if (startedAt === null) {
+    if (next >= frames.length) {
+      restart();
+    }
+    startedAt = performance.now() - position * 1000;
+    playButton.textContent = "Pause";
+    tick();
+  } else {
+    startedAt = null;
+    playButton.textContent = "Play";
+  }
+});
+document.getElementById("restart").addEventListener("click", restart);
+
+startedAt = performance.now();
+tick();
+</script>
+</body>
+</html>
+"#;
+
+// A web page replaying every output event.
+fn export_html(events: &[(Duration, String)], options: &ExportOpts, path: &Path) -> AnyResult<()> {
+    let mut vterm = VirtualTerminal::new(options.columns, options.rows);
+    let mut shown_rows = vec![String::new(); options.rows as usize];
+    let mut shown_title = String::new();
+    let mut shown_cursor = vterm.cursor();
+    let mut frames = String::from("[");
+
+    for (time, output) in events {
+        vterm.feed(output);
+        let html_rows: Vec<String> = vterm.rows().iter().map(|row| row_to_html(row)).collect();
+        let changed_rows: Vec<String> = html_rows
+            .iter()
+            .enumerate()
+            .filter(|&(index, html)| shown_rows.get(index) != Some(html))
+            .map(|(index, html)| format!("[{},{}]", index, json_string(html)))
+            .collect();
+        let title = if vterm.title() != shown_title {
+            shown_title = vterm.title().to_string();
+            json_string(&shown_title)
+        } else {
+            "null".to_string()
+        };
+        if changed_rows.is_empty()
+            && title == "null"
+            && vterm.cursor() == shown_cursor
+            && html_rows.len() == shown_rows.len()
+        {
+            continue;
+        }
+        shown_cursor = vterm.cursor();
+        let cursor = shown_cursor.map_or("null".to_string(), |(column, row)| {
+            format!("[{},{}]", column, row)
+        });
+        if frames.len() > 1 {
+            frames.push_str(",\n");
+        }
+        let _ = write!(
+            frames,
+            "[{:.3},[{}],{},{},{}]",
+            time.as_secs_f64(),
+            changed_rows.join(","),
+            title,
+            cursor,
+            html_rows.len()
+        );
+        shown_rows = html_rows;
+    }
+    frames.push(']');
+
+    let page_title = if shown_title.is_empty() {
+        "Animessage"
+    } else {
+        &shown_title
+    };
+    let html = HTML_PLAYER
+        .replace("__TITLE__", &escape_html(page_title))
+        .replace("__COLUMNS__", &vterm.size().0.to_string())
+        .replace("__FOREGROUND__", &hex_color(options.theme.foreground))
+        .replace("__BACKGROUND__", &hex_color(options.theme.background))
+        .replace("__FRAMES__", &frames.replace("</", "<\\/")); // "</script>" in the text would end the script.
+    fs::write(path, html)?;
+    Ok(())
+}
+
+// Runs of cells with the same colors become spans. Trailing blank cells are left out.
+fn row_to_html(row: &[Cell]) -> String {
+    let end = row
+        .iter()
+        .rposition(|cell| cell.chr != ' ' || cell.bg != CellColor::Default)
+        .map_or(0, |index| index + 1);
+    let mut html = String::new();
+    let mut pen = (CellColor::Default, CellColor::Default);
+    for cell in &row[..end] {
+        if (cell.fg, cell.bg) != pen {
+            if pen != (CellColor::Default, CellColor::Default) {
+                html.push_str("</span>");
+            }
+            pen = (cell.fg, cell.bg);
+            if pen != (CellColor::Default, CellColor::Default) {
+                let mut style = String::new();
+                if let Some(color) = css_color(cell.fg) {
+                    let _ = write!(style, "color:{};", color);
+                }
+                if let Some(color) = css_color(cell.bg) {
+                    let _ = write!(style, "background:{};", color);
+                }
+                let _ = write!(html, "<span style=\"{}\">", style);
+            }
+        }
+        html.push_str(&escape_html(&cell.chr.to_string()));
+    }
+    if pen != (CellColor::Default, CellColor::Default) {
+        html.push_str("</span>");
+    }
+    html
+}
+
+// The default colors are left to the theme.
+fn css_color(color: CellColor) -> Option<String> {
+    match color {
+        CellColor::Default => None,
+        CellColor::Indexed(n) => Some(hex_color(indexed_to_rgb(n))),
+        CellColor::Rgb(r, g, b) => Some(hex_color((r, g, b))),
+    }
+}
+
+fn hex_color((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// An SVG image showing every snapshot side by side, and moved in front of the view one after the other with CSS keyframes.
+fn export_svg(snapshots: &[Snapshot], options: &ExportOpts, path: &Path) -> AnyResult<()> {
+    let (cell_width, cell_height) = SVG_CELL_SIZE;
+    let (width, height) = (
+        options.columns as f32 * cell_width,
+        options.rows as f32 * cell_height,
+    );
+    let total: Duration = snapshots.iter().map(|snapshot| snapshot.duration).sum();
+    let theme = options.theme;
+
+    let mut keyframes = String::new();
+    let mut frames = String::new();
+    let mut start = Duration::ZERO;
+    for (index, snapshot) in snapshots.iter().enumerate() {
+        let _ = writeln!(
+            keyframes,
+            "  {:.4}% {{ transform: translateX({:.1}px); }}",
+            start.as_secs_f64() / total.as_secs_f64() * 100.0,
+            0.0 - index as f32 * width // Avoids "-0.0".
+        );
+        start += snapshot.duration;
+
+        let _ = writeln!(
+            frames,
+            "<g transform=\"translate({:.1} 0)\">",
+            index as f32 * width
+        );
+        for (row_index, row) in snapshot.rows.iter().enumerate().take(options.rows as usize) {
+            let y = row_index as f32 * cell_height;
+            let row = &row[..row.len().min(options.columns as usize)];
+            let mut column = 0;
+            for run in row.chunk_by(|a, b| (a.fg, a.bg) == (b.fg, b.bg)) {
+                let x = column as f32 * cell_width;
+                let run_width = run.len() as f32 * cell_width;
+                column += run.len();
+                if run[0].bg != CellColor::Default {
+                    let _ = writeln!(
+                        frames,
+                        "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>",
+                        x,
+                        y,
+                        run_width,
+                        cell_height,
+                        hex_color(theme.rgb(run[0].bg, true))
+                    );
+                }
+                let text: String = run.iter().map(|cell| cell.chr).collect();
+                let text = text.trim_end();
+                if text.trim_start().is_empty() {
+                    continue;
+                }
+                let _ = writeln!(
+                    frames,
+                    "<text x=\"{:.1}\" y=\"{:.1}\" fill=\"{}\" textLength=\"{:.1}\">{}</text>",
+                    x,
+                    y + cell_height * 0.75,
+                    hex_color(theme.rgb(run[0].fg, false)),
+                    text.chars().count() as f32 * cell_width,
+                    escape_html(text)
+                );
+            }
+        }
+        if let Some((column, row)) = snapshot.cursor {
+            let _ = writeln!(
+                frames,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\" opacity=\"0.7\"/>",
+                column as f32 * cell_width,
+                row as f32 * cell_height,
+                cell_width,
+                cell_height,
+                hex_color(theme.foreground)
+            );
+        }
+        frames.push_str("</g>\n");
+    }
+
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.1}" height="{height:.1}" viewBox="0 0 {width:.1} {height:.1}">
+<style>
+text {{ font-family: "DejaVu Sans Mono", Menlo, Consolas, monospace; font-size: 14px; white-space: pre; }}
+#frames {{ animation: play {total:.3}s steps(1, end) infinite; }}
+@keyframes play {{
+{keyframes}}}
+</style>
+<rect width="100%" height="100%" fill="{background}"/>
+<g id="frames">
+{frames}</g>
+</svg>
+"#,
+        width = width,
+        height = height,
+        total = total.as_secs_f64(),
+        keyframes = keyframes,
+        background = hex_color(theme.background),
+        frames = frames
+    );
+    fs::write(path, svg)?;
+    Ok(())
+}
+
+// A GIF image drawn with the built-in bitmap font.
+fn export_gif(snapshots: &[Snapshot], options: &ExportOpts, path: &Path) -> AnyResult<()> {
+    let mut encoder =
+        GifEncoder::new_with_speed(BufWriter::new(File::create(path)?), GIF_ENCODER_SPEED);
+    encoder.set_repeat(Repeat::Infinite)?;
+    for snapshot in snapshots {
+        let image = rasterize(snapshot, options);
+        let delay = Delay::from_saturating_duration(snapshot.duration);
+        encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+    }
+    Ok(())
+}
+
+fn rasterize(snapshot: &Snapshot, options: &ExportOpts) -> RgbaImage {
+    let (cell_width, cell_height) = (CELL_SIZE.0 * GIF_SCALE, CELL_SIZE.1 * GIF_SCALE);
+    let rgba = |(r, g, b): (u8, u8, u8)| Rgba([r, g, b, 255]);
+    let theme = options.theme;
+    let mut image = RgbaImage::from_pixel(
+        options.columns as u32 * cell_width,
+        options.rows as u32 * cell_height,
+        rgba(theme.background),
+    );
+    for (row_index, row) in snapshot.rows.iter().enumerate() {
+        for (column, cell) in row.iter().enumerate() {
+            let (x, y) = (column as u32 * cell_width, row_index as u32 * cell_height);
+            let (mut fg, mut bg) = (theme.rgb(cell.fg, false), theme.rgb(cell.bg, true));
+            if snapshot.cursor == Some((column, row_index)) {
+                std::mem::swap(&mut fg, &mut bg);
+            }
+            if bg != theme.background {
+                fill_rect(&mut image, x, y, cell_width, cell_height, rgba(bg));
+            }
+            draw_char(&mut image, cell.chr, x, y, GIF_SCALE, rgba(fg));
+        }
+    }
+    image
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_exported_with_their_colors() {
+        let mut vterm = VirtualTerminal::new(10, 2);
+        vterm.feed("a<b \u{1b}[31mred\u{1b}[0m");
+        assert_eq!(
+            row_to_html(&vterm.rows()[0]),
+            "a&lt;b <span style=\"color:#800000;\">red</span>"
+        );
+        assert_eq!(row_to_html(&vterm.rows()[1]), "");
+    }
+
+    #[test]
+    fn identical_samples_are_merged() {
+        let options =
+            ExportOpts::from_iter(&["export", "--columns", "10", "--rows", "2", "--fps", "10"]);
+        let events = vec![
+            (Duration::ZERO, "a".to_string()),
+            (Duration::from_millis(50), "b".to_string()), // Between two samples.
+            (Duration::from_secs(1), "c".to_string()),
+        ];
+        let snapshots = snapshots(&events, &options);
+        let durations: Vec<u128> = snapshots
+            .iter()
+            .map(|snapshot| snapshot.duration.as_millis())
+            .collect();
+        assert_eq!(durations, vec![100, 900, 100 + END_PAUSE.as_millis()]);
+        assert_eq!(snapshots[1].rows[0][1].chr, 'b');
+    }
+}
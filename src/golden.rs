@@ -0,0 +1,113 @@
+//! Golden tests : the animessages of `tests/goldens` are played headlessly, and the screen after each print and marker
+//! is compared to the `.golden` file next to them. Run the tests with `ANIMESSAGE_UPDATE_GOLDENS=1` to write the goldens again.
+
+use std::{env, rc::Rc};
+
+use super::*;
+
+const GOLDENS_DIR: &str = "tests/goldens";
+const UPDATE_GOLDENS_VAR: &str = "ANIMESSAGE_UPDATE_GOLDENS";
+
+/// Describes the screen in memory at each mark.
+#[derive(Clone)]
+struct SnapshotRenderer {
+    screen: MemoryRenderer,
+    snapshots: Rc<RefCell<String>>,
+}
+
+impl Renderer for SnapshotRenderer {
+    fn render(&mut self, time: Duration, output: &str) -> io::Result<()> {
+        self.screen.render(time, output)
+    }
+
+    fn mark(&mut self, time: Duration, label: &str) -> io::Result<()> {
+        let snapshot = snapshot(&self.screen.screen(), time, label);
+        self.snapshots.borrow_mut().push_str(&snapshot);
+        Ok(())
+    }
+}
+
+fn snapshot(vterm: &VirtualTerminal, time: Duration, label: &str) -> String {
+    let mut snapshot = format!("=== {} at {:.3}s ===\n", label, time.as_secs_f64());
+    let cursor = vterm
+        .cursor()
+        .map_or("hidden".to_string(), |(column, row)| {
+            format!("{},{}", column, row)
+        });
+    snapshot.push_str(&format!("title {:?}, cursor {}\n", vterm.title(), cursor));
+
+    let text = vterm.text();
+    let text = text.trim_end_matches('\n'); // The rows below the last printed one.
+    for line in text.lines() {
+        snapshot.push_str(&format!("|{}\n", line));
+    }
+    for (row_index, row) in vterm.rows().iter().enumerate() {
+        let mut column = 0;
+        for run in row.chunk_by(|a, b| (a.fg, a.bg) == (b.fg, b.bg)) {
+            if (run[0].fg, run[0].bg) != (CellColor::Default, CellColor::Default) {
+                snapshot.push_str(&format!(
+                    "colors of row {}, columns {} to {} : {:?} on {:?}\n",
+                    row_index,
+                    column,
+                    column + run.len() - 1,
+                    run[0].fg,
+                    run[0].bg
+                ));
+            }
+            column += run.len();
+        }
+    }
+    snapshot
+}
+
+/// Plays an animessage headlessly and describes the screen after each print and marker.
+fn play(animessage: &str) -> AnyResult<String> {
+    let renderer = SnapshotRenderer {
+        screen: MemoryRenderer::new(80, 24),
+        snapshots: Rc::default(),
+    };
+    let mut stage = Stage::new()
+        .with_headless(true)
+        .with_renderer(renderer.clone());
+    let mut audio = Audio::new(false);
+    display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio)?;
+    let snapshots = renderer.snapshots.borrow().clone();
+    Ok(snapshots)
+}
+
+#[test]
+fn screens_match_goldens() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDENS_DIR);
+    let update = env::var_os(UPDATE_GOLDENS_VAR).is_some();
+    let mut animessages: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "anim")
+        })
+        .collect();
+    animessages.sort();
+    assert!(!animessages.is_empty());
+
+    let mut mismatches = Vec::new();
+    for path in animessages {
+        let snapshots = play(&fs::read_to_string(&path).unwrap()).unwrap();
+        let golden_path = path.with_extension("golden");
+        if update {
+            fs::write(&golden_path, &snapshots).unwrap();
+        } else if fs::read_to_string(&golden_path).ok().as_deref() != Some(snapshots.as_str()) {
+            eprintln!(
+                "{:?} doesn't match. Its screens are :\n{}",
+                golden_path, snapshots
+            );
+            mismatches.push(golden_path);
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "These goldens don't match : {:?}. If the changes are expected, run the tests again with {}=1.",
+        mismatches,
+        UPDATE_GOLDENS_VAR
+    );
+}
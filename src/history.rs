@@ -0,0 +1,219 @@
+use std::rc::Rc;
+
+use super::*;
+
+/// What the functions of the animessage have changed, to play it again from a line as it was.
+#[derive(Clone)]
+pub(crate) struct PlayState {
+    pub(crate) line_index: usize,
+    pub(crate) lines: Rc<Vec<String>>, // Lines as modified by --[REPLACE]--, --[DEL_LINE]-- and --[INCLUDE]--, shared until they change.
+    pub(crate) vars: HashMap<String, Variable>,
+    pub(crate) gotos_cache: HashMap<usize, u64>,
+    pub(crate) replaces_cache: HashMap<usize, [String; 2]>,
+    pub(crate) current_step: String,
+}
+
+/// What was on screen. Rows which haven't changed since the previous checkpoint are shared with it.
+pub(crate) struct ScreenCheckpoint {
+    rows: Vec<Rc<Vec<Cell>>>,
+    title: String,
+    cursor: Option<(usize, usize)>,
+}
+
+impl ScreenCheckpoint {
+    pub(crate) fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.rows.iter().map(|row| row.as_slice())
+    }
+
+    pub(crate) fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub(crate) fn cursor(&self) -> Option<(usize, usize)> {
+        self.cursor
+    }
+}
+
+/// What the animessage had done at the end of a step, to start again from there.
+pub(crate) struct Checkpoint {
+    pub(crate) state: PlayState, // Its line is the one after the step.
+    pub(crate) screen: ScreenCheckpoint,
+}
+
+/// Checkpoints of the steps shown so far, the first one being the start of the animessage.
+/// Those gone back from are kept to go forward again, until a new step ends.
+#[derive(Default)]
+pub(crate) struct History {
+    checkpoints: Vec<Checkpoint>,
+    undone: Vec<Checkpoint>,
+}
+
+impl History {
+    pub(crate) fn record(&mut self, state: PlayState, screen: &VirtualTerminal) {
+        let previous_rows = self
+            .checkpoints
+            .last()
+            .map(|checkpoint| &checkpoint.screen.rows);
+        let rows = screen
+            .rows()
+            .iter()
+            .enumerate()
+            .map(
+                |(index, row)| match previous_rows.and_then(|rows| rows.get(index)) {
+                    Some(previous_row) if previous_row.as_slice() == row.as_slice() => {
+                        Rc::clone(previous_row)
+                    }
+                    _ => Rc::new(row.clone()),
+                },
+            )
+            .collect();
+        let screen = ScreenCheckpoint {
+            rows,
+            title: screen.title().to_string(),
+            cursor: screen.cursor(),
+        };
+        self.checkpoints.push(Checkpoint { state, screen });
+        self.undone.clear();
+    }
+
+    /// Undoes the last step and returns the checkpoint of the one before, which is kept to go back further.
+    pub(crate) fn rewind(&mut self) -> Option<&Checkpoint> {
+        if self.checkpoints.len() > 1 {
+            self.undone.extend(self.checkpoints.pop());
+        }
+        self.checkpoints.last()
+    }
+
+    /// Redoes the last step undone, returning its checkpoint.
+    pub(crate) fn forward(&mut self) -> Option<&Checkpoint> {
+        let checkpoint = self.undone.pop()?;
+        self.checkpoints.push(checkpoint);
+        self.checkpoints.last()
+    }
+
+    pub(crate) fn can_go_forward(&self) -> bool {
+        !self.undone.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, sync::mpsc};
+
+    use super::*;
+
+    // Labels of the steps which have ended.
+    #[derive(Clone, Default)]
+    struct MarksRenderer {
+        marks: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Renderer for MarksRenderer {
+        fn render(&mut self, _time: Duration, _output: &str) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn mark(&mut self, _time: Duration, label: &str) -> io::Result<()> {
+            self.marks.borrow_mut().push(label.to_string());
+            Ok(())
+        }
+    }
+
+    fn state(line_index: usize) -> PlayState {
+        PlayState {
+            line_index,
+            lines: Rc::default(),
+            vars: HashMap::new(),
+            gotos_cache: HashMap::new(),
+            replaces_cache: HashMap::new(),
+            current_step: String::new(),
+        }
+    }
+
+    #[test]
+    fn steps_can_be_gone_back_and_forward_through() {
+        let mut screen = VirtualTerminal::new(20, 3);
+        let mut history = History::default();
+        history.record(state(0), &screen);
+        screen.feed("Step 1\n");
+        history.record(state(2), &screen);
+        screen.feed("Step 2");
+        history.record(state(4), &screen);
+        let rows = |checkpoint: &Checkpoint| checkpoint.screen.rows.clone();
+        let (first, last) = (rows(&history.checkpoints[1]), rows(&history.checkpoints[2]));
+        assert!(Rc::ptr_eq(&first[0], &last[0])); // "Step 1" is only kept once.
+        assert!(!Rc::ptr_eq(&first[1], &last[1]));
+
+        assert_eq!(
+            history
+                .rewind()
+                .map(|checkpoint| checkpoint.state.line_index),
+            Some(2)
+        );
+        assert_eq!(
+            history
+                .rewind()
+                .map(|checkpoint| checkpoint.state.line_index),
+            Some(0)
+        );
+        assert_eq!(
+            history
+                .rewind()
+                .map(|checkpoint| checkpoint.state.line_index),
+            Some(0)
+        ); // The start is kept.
+        assert_eq!(
+            history
+                .forward()
+                .map(|checkpoint| checkpoint.state.line_index),
+            Some(2)
+        );
+        history.record(state(3), &screen); // A new step ends, so the one gone back from is forgotten.
+        assert!(!history.can_go_forward());
+    }
+
+    #[test]
+    fn going_back_while_typing_shows_the_previous_step_again() {
+        let (sender, receiver) = mpsc::channel();
+        sender.send(Control::PreviousStep.key()).unwrap(); // Handled while "Step 3" is typed.
+        let renderer = MarksRenderer::default();
+        let mut stage = Stage::new()
+            .with_renderer(renderer.clone())
+            .with_controls(Controls::from_receiver(receiver));
+        let mut audio = Audio::new(false);
+        let animessage =
+            "Step 1\n--[PRINT]-- \"0\"\nStep 2\n--[PRINT]-- \"0\"\nStep 3\n--[PRINT]-- \"0.01\"";
+        display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio).unwrap();
+
+        // The half-typed step 3 doesn't end, so going back plays step 2 again.
+        let marks = renderer.marks.borrow();
+        assert_eq!(
+            *marks,
+            [
+                "PRINT line 2",
+                "PRINT line 4",
+                "PRINT line 4",
+                "PRINT line 6"
+            ]
+        );
+    }
+
+    #[test]
+    fn going_back_restores_the_lines_and_the_screen() {
+        let (sender, receiver) = mpsc::channel();
+        sender.send(Control::PreviousStep.key()).unwrap(); // Handled during the first wait, after the second step.
+        let screen = MemoryRenderer::new(80, 24);
+        let mut stage = Stage::new()
+            .with_renderer(screen.clone())
+            .with_controls(Controls::from_receiver(receiver));
+        let mut audio = Audio::new(false);
+        // Played again after going back, the --[DEL_LINE]-- would delete "Kept" too if the lines weren't restored.
+        let animessage = "Step 1\n--[PRINT]-- \"0\"\n--[DEL_LINE]-- \"5\"\nStep 2\nDeleted\nKept\n--[PRINT]-- \"0\"\n--[WAIT]-- \"0.1\"\nEnd\n--[PRINT]-- \"0\"";
+        display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio).unwrap();
+
+        assert_eq!(
+            screen.screen().text().trim_end(),
+            "Step 1\nStep 2\nKept\nEnd"
+        );
+    }
+}
@@ -0,0 +1,471 @@
+use image::{
+    codecs::gif::GifDecoder, imageops::FilterType, AnimationDecoder, DynamicImage, Frame,
+    GenericImageView, Rgba,
+};
+
+use super::*;
+
+const ASCII_RAMP: &[char] = &[' ', '.', ':', '-', '=', '+', '*', '#', '%', '@']; // From dark to bright.
+const UPPER_HALF_BLOCK: char = '▀';
+const LOWER_HALF_BLOCK: char = '▄';
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100); // Like web browsers do for GIFs with a frame delay of 10 ms or less.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ImageMode {
+    Auto,      // Graphics protocol of the terminal if there's one, else one of the modes below.
+    Ascii,     // Characters ramp, no color.
+    TrueColor, // Half blocks with 24-bit colors.
+    Colors256, // Half blocks with the 256 colors palette.
+    Colors16,  // Half blocks with the 16 basic colors.
+}
+
+impl FromStr for ImageMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ImageMode::Auto),
+            "ascii" => Ok(ImageMode::Ascii),
+            "truecolor" => Ok(ImageMode::TrueColor),
+            "256" => Ok(ImageMode::Colors256),
+            "16" => Ok(ImageMode::Colors16),
+            _ => anyhow::bail!(
+                "No image mode with this name. Use \"auto\", \"ascii\", \"truecolor\", \"256\" or \"16\"."
+            ),
+        }
+    }
+}
+
+pub(crate) struct ImageOptions {
+    pub(crate) width: Option<u32>,  // In columns.
+    pub(crate) height: Option<u32>, // In rows.
+    pub(crate) position: Option<Position>,
+    pub(crate) mode: ImageMode,
+    pub(crate) loops: u32, // How many times an animated image is played. 0 plays it until `max_duration` is reached.
+    pub(crate) max_duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CellColor {
+    Default,
+    Rgb(u8, u8, u8),
+    Indexed(u8), // 0 to 15 are the basic colors, 16 to 255 the extended palette.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Cell {
+    pub(crate) chr: char,
+    pub(crate) fg: CellColor,
+    pub(crate) bg: CellColor,
+}
+
+impl Cell {
+    pub(crate) fn blank() -> Self {
+        Cell {
+            chr: ' ',
+            fg: CellColor::Default,
+            bg: CellColor::Default,
+        }
+    }
+}
+
+/// Shows an image file, either with the terminal's graphics protocol or as text so it also works without a TTY.
+/// Animated GIFs are played in place as text.
+pub(crate) fn print_image(stage: &mut Stage, path: &Path, options: &ImageOptions) -> AnyResult<()> {
+    if is_gif(path) {
+        return play_gif(stage, path, options);
+    }
+
+    let mode = match options.mode {
+        ImageMode::Auto if stage.is_composited() || stage.is_recording() => ImageMode::TrueColor,
+        ImageMode::Auto if !stage.is_tty() => ImageMode::Ascii,
+        ImageMode::Auto => match print_with_viuer(stage, path, options) {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                warn!(
+                    "Can't print the image with the terminal's graphics. Falling back to colored text. Error details : {:?}",
+                    err
+                );
+                ImageMode::TrueColor
+            }
+        },
+        mode => mode,
+    };
+
+    let image = image::open(path)?;
+    let (columns, rows) = cells_size(
+        image.dimensions(),
+        options.width,
+        options.height,
+        max_columns(stage),
+    );
+    let cells = render(&image, columns, rows, mode);
+    stage.print_cells(&cells, options.position);
+    Ok(())
+}
+
+pub(crate) fn max_columns(stage: &Stage) -> u32 {
+    stage.size().0 as u32
+}
+
+pub(crate) fn is_gif(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"))
+}
+
+/// Mode to use for content that can only be shown as text, such as animations.
+pub(crate) fn text_image_mode(stage: &Stage, mode: ImageMode) -> ImageMode {
+    match mode {
+        ImageMode::Auto if stage.is_composited() || stage.is_recording() || stage.is_tty() => {
+            ImageMode::TrueColor
+        }
+        ImageMode::Auto => ImageMode::Ascii,
+        mode => mode,
+    }
+}
+
+/// Delay of an animation frame, with the same default as web browsers for the very short ones.
+pub(crate) fn frame_delay(frame: &Frame) -> Duration {
+    let (numerator, denominator) = frame.delay().numer_denom_ms();
+    let delay = Duration::from_secs_f64(numerator as f64 / denominator.max(1) as f64 / 1000.0);
+    if delay <= Duration::from_millis(10) {
+        DEFAULT_FRAME_DELAY
+    } else {
+        delay
+    }
+}
+
+fn play_gif(stage: &mut Stage, path: &Path, options: &ImageOptions) -> AnyResult<()> {
+    let mode = text_image_mode(stage, options.mode);
+
+    let decoder = GifDecoder::new(BufReader::new(File::open(path)?))?;
+    let frames = decoder.into_frames().collect_frames()?;
+    let first_frame = frames
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("This GIF has no frames."))?;
+    let (columns, rows) = cells_size(
+        first_frame.buffer().dimensions(),
+        options.width,
+        options.height,
+        max_columns(stage),
+    );
+
+    let rendered: Vec<(Vec<Vec<Cell>>, Duration)> = frames
+        .into_iter()
+        .map(|frame| {
+            let delay = frame_delay(&frame);
+            let image = DynamicImage::ImageRgba8(frame.into_buffer());
+            (render(&image, columns, rows, mode), delay)
+        })
+        .collect();
+
+    play_frames(
+        stage,
+        &rendered,
+        options.position,
+        options.loops,
+        options.max_duration,
+    );
+    Ok(())
+}
+
+/// Prints frames (cells, delay before the next one) over each other, `loops` times or forever if it's 0, stopping early after `max_duration`.
+/// The last frame shown stays on screen and the cursor ends up below it.
+pub(crate) fn play_frames(
+    stage: &mut Stage,
+    frames: &[(Vec<Vec<Cell>>, Duration)],
+    position: Option<Position>,
+    loops: u32,
+    max_duration: Option<Duration>,
+) {
+    let start = stage.now();
+    let loops = if loops == 0 && max_duration.is_none() && !stage.waits() {
+        warn!("Endless animations are played once when delays are skipped. Give them a \"duration\" to play them longer.");
+        1
+    } else {
+        loops
+    };
+    let mut played_loops = 0;
+    'playback: loop {
+        for (index, (cells, delay)) in frames.iter().enumerate() {
+            if (index > 0 || played_loops > 0) && position.is_none() {
+                stage.move_cursor_up(cells.len()); // Back to where the previous frame started.
+            }
+            stage.print_cells(cells, position);
+
+            let is_last_frame = index + 1 == frames.len() && played_loops + 1 == loops;
+            if is_last_frame || frames.len() == 1 {
+                break 'playback;
+            }
+
+            let delay = match max_duration {
+                Some(max_duration) => {
+                    let remaining = max_duration.saturating_sub(stage.now() - start);
+                    if remaining.is_zero() {
+                        break 'playback;
+                    }
+                    remaining.min(*delay)
+                }
+                None => *delay,
+            };
+            stage.sleep(delay);
+            if stage.is_interrupted() {
+                break 'playback;
+            }
+        }
+        played_loops += 1;
+    }
+}
+
+fn print_with_viuer(stage: &mut Stage, path: &Path, options: &ImageOptions) -> AnyResult<()> {
+    stage.flush(); // viuer writes to the terminal by itself.
+    let (x, y) = match options.position {
+        Some((x, y)) => (x.max(0) as u16, y.max(0) as u16),
+        None => cursor::position()?,
+    };
+    let conf = viuer::Config {
+        x,
+        y: y as i16,
+        width: options.width,
+        height: options.height,
+        use_kitty: true,
+        use_iterm: true,
+        // use_sixel: false,
+        ..Default::default()
+    };
+    viuer::print_from_file(path, &conf)?;
+    Ok(())
+}
+
+/// Size of the rendered image in (columns, rows). A missing dimension keeps the aspect ratio, knowing that a cell is about twice as high as it is wide.
+pub(crate) fn cells_size(
+    (image_width, image_height): (u32, u32),
+    width: Option<u32>,
+    height: Option<u32>,
+    max_columns: u32,
+) -> (u32, u32) {
+    let ratio = image_height.max(1) as f64 / image_width.max(1) as f64 / 2.0;
+    let (columns, rows) = match (width, height) {
+        (Some(w), Some(h)) => (w, h),
+        (Some(w), None) => (w, (w as f64 * ratio).round() as u32),
+        (None, Some(h)) => ((h as f64 / ratio).round() as u32, h),
+        (None, None) => {
+            let w = image_width.min(max_columns);
+            (w, (w as f64 * ratio).round() as u32)
+        }
+    };
+    (columns.max(1), rows.max(1))
+}
+
+/// Renders an image into rows of cells. Colored modes use half blocks, so each cell shows two pixels stacked vertically.
+pub(crate) fn render(
+    image: &DynamicImage,
+    columns: u32,
+    rows: u32,
+    mode: ImageMode,
+) -> Vec<Vec<Cell>> {
+    if mode == ImageMode::Ascii {
+        let resized = image.resize_exact(columns, rows, FilterType::Triangle);
+        return (0..rows)
+            .map(|y| {
+                (0..columns)
+                    .map(|x| {
+                        let pixel = resized.get_pixel(x, y);
+                        if !is_visible(pixel) {
+                            return Cell::blank();
+                        }
+                        let [r, g, b, _a] = pixel.0;
+                        let luma = 0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64;
+                        let index = (luma / 256.0 * ASCII_RAMP.len() as f64) as usize;
+                        Cell {
+                            chr: ASCII_RAMP[index.min(ASCII_RAMP.len() - 1)],
+                            ..Cell::blank()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+
+    let resized = image.resize_exact(columns, rows * 2, FilterType::Triangle);
+    (0..rows)
+        .map(|y| {
+            (0..columns)
+                .map(|x| {
+                    let top = resized.get_pixel(x, y * 2);
+                    let bottom = resized.get_pixel(x, y * 2 + 1);
+                    match (is_visible(top), is_visible(bottom)) {
+                        (false, false) => Cell::blank(),
+                        (true, false) => Cell {
+                            chr: UPPER_HALF_BLOCK,
+                            fg: quantize(top, mode),
+                            bg: CellColor::Default,
+                        },
+                        (false, true) => Cell {
+                            chr: LOWER_HALF_BLOCK,
+                            fg: quantize(bottom, mode),
+                            bg: CellColor::Default,
+                        },
+                        (true, true) => Cell {
+                            chr: UPPER_HALF_BLOCK,
+                            fg: quantize(top, mode),
+                            bg: quantize(bottom, mode),
+                        },
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn is_visible(pixel: Rgba<u8>) -> bool {
+    pixel.0[3] >= 128
+}
+
+fn quantize(pixel: Rgba<u8>, mode: ImageMode) -> CellColor {
+    let [r, g, b, _a] = pixel.0;
+    match mode {
+        ImageMode::Colors256 => CellColor::Indexed(rgb_to_256(r, g, b)),
+        ImageMode::Colors16 => CellColor::Indexed(rgb_to_16(r, g, b)),
+        _ => CellColor::Rgb(r, g, b),
+    }
+}
+
+// 6x6x6 color cube from 16 to 231 of the 256-color palette, then 24 shades of grey from 232 to 255.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+// The 16 basic colors, as rendered by most terminals.
+const BASIC_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (128, 0, 0),
+    (0, 128, 0),
+    (128, 128, 0),
+    (0, 0, 128),
+    (128, 0, 128),
+    (0, 128, 128),
+    (192, 192, 192),
+    (128, 128, 128),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (0, 0, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> i32 {
+    let (dr, dg, db) = (
+        r1 as i32 - r2 as i32,
+        g1 as i32 - g2 as i32,
+        b1 as i32 - b2 as i32,
+    );
+    dr * dr + dg * dg + db * db
+}
+
+fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    let nearest_level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - c as i32).abs())
+            .unwrap()
+    };
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube_color = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+    let grey_index = ((r as u32 + g as u32 + b as u32) / 3).saturating_sub(3) / 10;
+    let grey_index = grey_index.min(23) as u8;
+    let grey = 8 + grey_index * 10;
+
+    if distance((r, g, b), (grey, grey, grey)) < distance((r, g, b), cube_color) {
+        232 + grey_index
+    } else {
+        16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+    }
+}
+
+fn rgb_to_16(r: u8, g: u8, b: u8) -> u8 {
+    (0..16)
+        .min_by_key(|&i| distance((r, g, b), BASIC_COLORS[i as usize]))
+        .unwrap()
+}
+
+/// RGB value of a color of the 256-color palette.
+pub(crate) fn indexed_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => BASIC_COLORS[n as usize],
+        16..=231 => {
+            let n = n - 16;
+            (
+                CUBE_LEVELS[(n / 36) as usize],
+                CUBE_LEVELS[(n / 6 % 6) as usize],
+                CUBE_LEVELS[(n % 6) as usize],
+            )
+        }
+        _ => {
+            let grey = 8 + (n - 232) * 10;
+            (grey, grey, grey)
+        }
+    }
+}
+
+fn sgr_color(color: CellColor, background: bool) -> String {
+    let (basic, bright, extended) = if background {
+        (40, 100, 48)
+    } else {
+        (30, 90, 38)
+    };
+    match color {
+        CellColor::Default => format!("{}", extended + 1),
+        CellColor::Indexed(n) if n < 8 => format!("{}", basic + n as u16),
+        CellColor::Indexed(n) if n < 16 => format!("{}", bright + n as u16 - 8),
+        CellColor::Indexed(n) => format!("{};5;{}", extended, n),
+        CellColor::Rgb(r, g, b) => format!("{};2;{};{};{}", extended, r, g, b),
+    }
+}
+
+/// Turns a row of cells into text with ANSI color codes. Colors are reset at the end of the row.
+pub(crate) fn cells_to_ansi(row: &[Cell]) -> String {
+    let mut ansi = String::with_capacity(row.len() * 4);
+    let mut pen = (CellColor::Default, CellColor::Default);
+    for cell in row {
+        if (cell.fg, cell.bg) != pen {
+            ansi.push_str(&format!(
+                "\x1b[{};{}m",
+                sgr_color(cell.fg, false),
+                sgr_color(cell.bg, true)
+            ));
+            pen = (cell.fg, cell.bg);
+        }
+        ansi.push(cell.chr);
+    }
+    if pen != (CellColor::Default, CellColor::Default) {
+        ansi.push_str("\x1b[0m");
+    }
+    ansi
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_mode_has_no_escape_codes() {
+        let mut image = image::RgbaImage::new(4, 4);
+        for (x, _y, pixel) in image.enumerate_pixels_mut() {
+            *pixel = if x < 2 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            };
+        }
+        let cells = render(&DynamicImage::ImageRgba8(image), 4, 2, ImageMode::Ascii);
+        let lines: Vec<String> = cells.iter().map(|row| cells_to_ansi(row)).collect();
+        assert_eq!(lines, vec!["  @@", "  @@"]);
+    }
+
+    #[test]
+    fn size_keeps_aspect_ratio() {
+        assert_eq!(cells_size((100, 100), Some(40), None, 80), (40, 20));
+        assert_eq!(cells_size((100, 100), None, Some(20), 80), (40, 20));
+        assert_eq!(cells_size((200, 100), None, None, 80), (80, 20));
+    }
+}
@@ -0,0 +1,283 @@
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+
+use super::*;
+
+/// Where the keys expected by --[WAIT_FOR_INPUT]-- are read from.
+pub trait KeyReader {
+    /// Keys pressed since the last call, or held down for readers which can see it.
+    fn pressed_keys(&mut self) -> AnyResult<Vec<Keycode>>;
+}
+
+/// Keys typed in the terminal, read from its own input in raw mode. Works over SSH and without X11, but only sees keys
+/// while the terminal is focused, and modifiers such as LShift only along with another key.
+pub struct TerminalKeys {
+    was_raw: bool,
+}
+
+impl TerminalKeys {
+    /// Puts the terminal in raw mode, forgetting the keys typed before.
+    pub fn new() -> AnyResult<Self> {
+        let was_raw = terminal::is_raw_mode_enabled()?;
+        terminal::enable_raw_mode()?;
+        let keys = TerminalKeys { was_raw };
+        while event::poll(Duration::ZERO)? {
+            event::read()?;
+        }
+        Ok(keys)
+    }
+}
+
+impl Drop for TerminalKeys {
+    fn drop(&mut self) {
+        if !self.was_raw {
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+impl KeyReader for TerminalKeys {
+    fn pressed_keys(&mut self) -> AnyResult<Vec<Keycode>> {
+        let mut keys = Vec::new();
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                keys.extend(keycodes(key));
+            }
+        }
+        Ok(keys)
+    }
+}
+
+/// Keys held down on the whole keyboard, even when the terminal isn't focused. Needs X11 on Linux, so it doesn't work over SSH.
+pub struct GlobalKeys {
+    device_state: DeviceState,
+}
+
+impl GlobalKeys {
+    pub fn new() -> Self {
+        GlobalKeys {
+            device_state: DeviceState::new(),
+        }
+    }
+}
+
+impl Default for GlobalKeys {
+    fn default() -> Self {
+        GlobalKeys::new()
+    }
+}
+
+impl KeyReader for GlobalKeys {
+    fn pressed_keys(&mut self) -> AnyResult<Vec<Keycode>> {
+        Ok(self.device_state.get_keys())
+    }
+}
+
+/// Keys accepted by --[WAIT_FOR_INPUT]-- : "ANY" key, or some keys and combinations separated by spaces, such as "Y N Ctrl+K".
+#[derive(Debug, PartialEq)]
+pub(crate) enum ExpectedKeys {
+    Any,
+    OneOf(Vec<KeyCombination>),
+}
+
+/// Keys to press together, such as "Ctrl+K". "Ctrl", "Shift" and "Alt" are either the left or the right one.
+#[derive(Debug, PartialEq)]
+pub(crate) struct KeyCombination {
+    name: String,
+    keys: Vec<Vec<Keycode>>, // Each of these keys, where any of the alternatives can be pressed.
+}
+
+impl ExpectedKeys {
+    /// Name of the expected key or combination among `keys`, as written in the animessage. For "ANY", the name of the first key.
+    pub(crate) fn pressed(&self, keys: &[Keycode]) -> Option<String> {
+        match self {
+            ExpectedKeys::Any => keys.first().map(|key| format!("{:?}", key)),
+            ExpectedKeys::OneOf(combinations) => combinations
+                .iter()
+                .find(|combination| {
+                    combination
+                        .keys
+                        .iter()
+                        .all(|alternatives| alternatives.iter().any(|key| keys.contains(key)))
+                })
+                .map(|combination| combination.name.clone()),
+        }
+    }
+}
+
+impl FromStr for ExpectedKeys {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "ANY" {
+            return Ok(ExpectedKeys::Any);
+        }
+        let combinations = s
+            .split_whitespace()
+            .map(|name| {
+                let keys = name
+                    .split('+')
+                    .map(key_alternatives)
+                    .collect::<AnyResult<_>>()?;
+                Ok(KeyCombination {
+                    name: name.to_string(),
+                    keys,
+                })
+            })
+            .collect::<AnyResult<Vec<_>>>()?;
+        if combinations.is_empty() {
+            bail!("No key is given. Write a key such as \"Space\", several keys separated by spaces, or \"ANY\".");
+        }
+        Ok(ExpectedKeys::OneOf(combinations))
+    }
+}
+
+fn key_alternatives(name: &str) -> AnyResult<Vec<Keycode>> {
+    let mut chars = name.chars();
+    let keys = match (chars.next(), chars.next()) {
+        (Some(chr), None) => char_keycode(chr).into_iter().collect(),
+        _ => match name {
+            "Ctrl" => vec![Keycode::LControl, Keycode::RControl],
+            "Shift" => vec![Keycode::LShift, Keycode::RShift],
+            "Alt" => vec![Keycode::LAlt, Keycode::RAlt],
+            name => Keycode::from_str(name).into_iter().collect(),
+        },
+    };
+    if keys.is_empty() {
+        bail!("Key {:?} isn't supported or isn't a correct key. Please replace the key in your animessage with an alphanumeric key, or a special common key (such as LControl for example) instead.", name);
+    }
+    Ok(keys)
+}
+
+// Keys of a US keyboard which type this key event, with the modifiers held.
+pub(crate) fn keycodes(key: KeyEvent) -> Vec<Keycode> {
+    let mut keys = Vec::with_capacity(3);
+    let code = match key.code {
+        KeyCode::Char(chr) => char_keycode(chr),
+        KeyCode::Enter => Some(Keycode::Enter),
+        KeyCode::Esc => Some(Keycode::Escape),
+        KeyCode::Backspace => Some(Keycode::Backspace),
+        KeyCode::Tab | KeyCode::BackTab => Some(Keycode::Tab),
+        KeyCode::Up => Some(Keycode::Up),
+        KeyCode::Down => Some(Keycode::Down),
+        KeyCode::Left => Some(Keycode::Left),
+        KeyCode::Right => Some(Keycode::Right),
+        KeyCode::Home => Some(Keycode::Home),
+        KeyCode::End => Some(Keycode::End),
+        KeyCode::PageUp => Some(Keycode::PageUp),
+        KeyCode::PageDown => Some(Keycode::PageDown),
+        KeyCode::Insert => Some(Keycode::Insert),
+        KeyCode::Delete => Some(Keycode::Delete),
+        KeyCode::F(number) => Keycode::from_str(&format!("F{}", number)).ok(),
+        _ => None,
+    };
+    keys.extend(code);
+    let typed_uppercase = matches!(key.code, KeyCode::Char(chr) if chr.is_ascii_uppercase());
+    if key.modifiers.contains(KeyModifiers::SHIFT)
+        || typed_uppercase
+        || key.code == KeyCode::BackTab
+    {
+        keys.extend([Keycode::LShift, Keycode::RShift]);
+    }
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        keys.extend([Keycode::LControl, Keycode::RControl]);
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        keys.extend([Keycode::LAlt, Keycode::RAlt]);
+    }
+    keys
+}
+
+fn char_keycode(chr: char) -> Option<Keycode> {
+    let name = match chr.to_ascii_uppercase() {
+        letter @ 'A'..='Z' => letter.to_string(),
+        digit @ '0'..='9' => format!("Key{}", digit),
+        ' ' => "Space".to_string(),
+        // Symbols typed with Shift are on the key of another character.
+        '!' => "Key1".to_string(),
+        '@' => "Key2".to_string(),
+        '#' => "Key3".to_string(),
+        '$' => "Key4".to_string(),
+        '%' => "Key5".to_string(),
+        '^' => "Key6".to_string(),
+        '&' => "Key7".to_string(),
+        '*' => "Key8".to_string(),
+        '(' => "Key9".to_string(),
+        ')' => "Key0".to_string(),
+        '`' | '~' => "Grave".to_string(),
+        '-' | '_' => "Minus".to_string(),
+        '=' | '+' => "Equal".to_string(),
+        '[' | '{' => "LeftBracket".to_string(),
+        ']' | '}' => "RightBracket".to_string(),
+        '\\' | '|' => "BackSlash".to_string(),
+        ';' | ':' => "Semicolon".to_string(),
+        '\'' | '"' => "Apostrophe".to_string(),
+        ',' | '<' => "Comma".to_string(),
+        '.' | '>' => "Dot".to_string(),
+        '/' | '?' => "Slash".to_string(),
+        _ => return None,
+    };
+    Keycode::from_str(&name).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terminal_keys_have_the_names_of_the_keyboard() {
+        let key = |code, modifiers| keycodes(KeyEvent::new(code, modifiers));
+        assert_eq!(key(KeyCode::Char('a'), KeyModifiers::NONE), [Keycode::A]);
+        assert_eq!(
+            key(KeyCode::Char('A'), KeyModifiers::SHIFT),
+            [Keycode::A, Keycode::LShift, Keycode::RShift]
+        );
+        assert_eq!(
+            key(KeyCode::Char(' '), KeyModifiers::NONE),
+            [Keycode::Space]
+        );
+        assert_eq!(
+            key(KeyCode::Char('?'), KeyModifiers::NONE),
+            [Keycode::Slash]
+        );
+        assert_eq!(key(KeyCode::Char('7'), KeyModifiers::NONE), [Keycode::Key7]);
+        assert_eq!(key(KeyCode::F(5), KeyModifiers::NONE), [Keycode::F5]);
+        assert_eq!(
+            key(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            [Keycode::C, Keycode::LControl, Keycode::RControl]
+        );
+    }
+
+    #[test]
+    fn expected_keys_can_be_any_one_of_a_list_or_combinations() {
+        let any: ExpectedKeys = "ANY".parse().unwrap();
+        assert_eq!(any.pressed(&[Keycode::Dot]), Some("Dot".to_string()));
+        assert_eq!(any.pressed(&[]), None);
+
+        let expected: ExpectedKeys = "y N Ctrl+K 2".parse().unwrap();
+        assert_eq!(expected.pressed(&[Keycode::Y]), Some("y".to_string()));
+        assert_eq!(expected.pressed(&[Keycode::K]), None);
+        assert_eq!(
+            expected.pressed(&[Keycode::K, Keycode::RControl]),
+            Some("Ctrl+K".to_string())
+        );
+        assert_eq!(expected.pressed(&[Keycode::Key2]), Some("2".to_string()));
+
+        assert!("Ctrl+Nope".parse::<ExpectedKeys>().is_err());
+        assert!(" ".parse::<ExpectedKeys>().is_err());
+    }
+
+    #[test]
+    fn timeouts_go_on_or_to_a_marker() {
+        let screen = MemoryRenderer::new(80, 24);
+        let mut stage = Stage::new()
+            .with_headless(true)
+            .with_renderer(screen.clone());
+        let mut audio = Audio::new(false);
+        let animessage = "--[WAIT_FOR_INPUT]-- \"Y N\" \"timeout=3\" \"goto=late\" \"var=answer\"\nSkipped\n--[PRINT]-- \"0\"\n--[MARKER]-- \"late\"\n--[TITLE]-- \"answer=[$answer]\"\nToo late\n--[PRINT]-- \"0\"";
+        display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio).unwrap();
+
+        assert_eq!(stage.now(), Duration::from_secs(3));
+        assert_eq!(screen.screen().title(), "answer=[]");
+        assert_eq!(screen.screen().text().trim_end(), "Too late");
+    }
+}
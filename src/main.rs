@@ -14,6 +14,66 @@ use parser::*;
 mod term;
 use term::*;
 
+mod compositor;
+use compositor::*;
+
+mod clock;
+use clock::*;
+
+mod controls;
+use controls::*;
+
+mod keys;
+use keys::*;
+
+mod history;
+use history::*;
+
+mod stage;
+use stage::*;
+
+mod slides;
+use slides::*;
+
+mod renderer;
+use renderer::*;
+
+mod recorder;
+use recorder::*;
+
+mod vterm;
+use vterm::*;
+
+mod export;
+use export::*;
+
+mod stats;
+use stats::*;
+
+#[cfg(test)]
+mod golden;
+
+mod font;
+use font::*;
+
+mod sprite;
+use sprite::*;
+
+mod image_render;
+use image_render::*;
+
+mod video;
+use video::*;
+
+mod audio;
+use audio::*;
+
+mod speech;
+use speech::*;
+
+mod tone;
+use tone::*;
+
 // #![no_mangle]
 
 use {
@@ -22,17 +82,20 @@ use {
     }, */
     /* lazy_static::lazy_static, */
     anyhow::Result as AnyResult,
-    args::{duration_from_arg, Args},
+    args::{
+        duration_from_arg, position_from_arg, size_from_arg, speed_from_arg, volume_from_arg, Args,
+    },
     device_query::{DeviceQuery, DeviceState, Keycode},
     image::{self /* GenericImageView */},
     log::{debug /*,  info */, error, warn},
     read_input::prelude::*,
-    rodio::{self, Source},
+    rodio::{self /* Source */},
     simple_logger::SimpleLogger,
     std::{
         /* borrow::Cow, */
         /* env, */
         collections::{BTreeMap, HashMap},
+        fmt,
         fs::{read_to_string, /* self, */ File},
         io::{self, BufReader, Write},
         path::{Path, PathBuf},
@@ -41,7 +104,7 @@ use {
         time::Duration,
     },
     structopt::StructOpt,
-    variable::Variable,
+    variable::{substitute_vars, Variable},
 };
 
 mod args;
@@ -66,6 +129,7 @@ fn check_relative_path_ok(path: &Path, relative_paths_ok: bool) {
 }
 
 fn print_title(stdout: &Term) {
+    let _ = terminal::disable_raw_mode(); // In case the playback controls are still running, when exiting early.
     println!(
         "
 
@@ -113,21 +177,16 @@ fn process_markers(
     let anim_lines_iter = animessage_str.lines().enumerate();
     match mode {
         MarkerMode::Find => {
-            for (index, l) in anim_lines_iter {
-                if l.starts_with(MARKER) {
-                    let args = Args::parse(l, 1)?;
-                    // TODO : Add one more arg to set an optional line to end with. -1 would be the last line.
-                    if args.get(0) == marker {
-                        if debug {
-                            debug!(
-                                "Found a corresponding marker to {:?} at line {}",
-                                marker,
-                                index + 1
-                            )
-                        }
-                        return Ok(index);
-                    }
+            // TODO : Add one more arg to set an optional line to end with. -1 would be the last line.
+            if let Some(index) = find_marker(animessage_str.lines(), marker) {
+                if debug {
+                    debug!(
+                        "Found a corresponding marker to {:?} at line {}",
+                        marker,
+                        index + 1
+                    )
                 }
+                return Ok(index);
             }
         }
         MarkerMode::Summary => {
@@ -178,9 +237,9 @@ fn main() -> AnyResult<()> {
 
     // Get cmd args
     let options = Opts::from_args();
-    let file = options.file;
+    let mut file = options.file;
     let tutorial = options.tutorial;
-    let no_exec = options.no_exec;
+    let mut no_exec = options.no_exec;
     let debug = if options.no_exec {
         debug!("Debug mode has been enabled by default because no_exec is enabled.");
         true
@@ -189,6 +248,73 @@ fn main() -> AnyResult<()> {
     };
     let marker = options.marker;
     let markers_summary = options.summary;
+    let mut stats = false;
+    let export = match options.command {
+        Some(Command::Export(mut export)) => {
+            if export.html.is_none() && export.svg.is_none() && export.gif.is_none() {
+                bail!("Choose a format to export to, such as \"export --html hello.html\".");
+            }
+            if export.columns == 0 || export.rows == 0 || export.fps == 0 {
+                bail!("The number of columns, rows and frames per second of an export must be greater than 0.");
+            }
+            let current_dir = std::env::current_dir()?; // Paths are relative to it, but it changes below.
+            for path in [&mut export.html, &mut export.svg, &mut export.gif]
+                .into_iter()
+                .flatten()
+            {
+                *path = current_dir.join(&path);
+            }
+            Some(export)
+        }
+        Some(Command::Stats(stats_opts)) => {
+            stats = true;
+            no_exec = true; // The stats are added up from the lines instead of playing them.
+            if stats_opts.file.is_some() {
+                file = stats_opts.file;
+            }
+            None
+        }
+        None => None,
+    };
+    let headless = options.headless || export.is_some() || stats;
+    let mut stage = Stage::new();
+    if !headless {
+        stage = stage.with_renderer(TerminalRenderer::new(stdout.clone()));
+    }
+    let output_log = OutputLog::default();
+    if let Some(export) = &export {
+        stage = stage
+            .with_size(export.columns, export.rows)
+            .with_renderer(output_log.clone());
+    }
+    let (columns, rows) = stage.size();
+    if let Some(cast_path) = &options.record {
+        stage = stage.with_renderer(CastRecorder::create(cast_path, columns, rows)?);
+    }
+    if let Some(transcript_path) = &options.transcript {
+        stage = stage.with_renderer(TextRenderer::create(transcript_path, columns, rows)?);
+    }
+    if let Some(speed) = options.speed {
+        stage = stage.with_clock(ScaledClock::new(speed));
+    }
+    if options.instant {
+        stage = stage.with_clock(VirtualClock::default());
+    }
+    let mut stage = stage
+        .with_headless(headless)
+        .with_compositor(options.compositor)
+        .with_global_keys(options.global_keys)
+        .with_playback_keys(!options.no_controls)
+        .with_present(options.present)
+        .with_slide_footer(!options.no_footer);
+    if !debug && stage.is_tty() {
+        match Controls::start() {
+            Ok(controls) => stage = stage.with_controls(controls),
+            Err(err) => warn!("The playback keys can't be used. Error details : {}", err),
+        }
+    }
+    let mut audio = Audio::new(!options.no_audio && !headless && !options.instant)
+        .with_speak_steps(options.speak);
 
     // #[cfg(windows)]
     // {
@@ -236,10 +362,26 @@ fn main() -> AnyResult<()> {
                         0
                     };
 
-                    display_animessage(&buf, true, debug, no_exec, start_index, &stdout)?;
+                    display_animessage(
+                        &buf,
+                        true,
+                        debug,
+                        no_exec,
+                        start_index,
+                        &mut stage,
+                        &mut audio,
+                    )?;
                 }
 
-                print_title(&stdout);
+                stage.release_controls();
+                if let Some(export) = &export {
+                    self::export(&output_log.events(), export)?;
+                }
+                if stats {
+                    println!("{}", stage.stats().report());
+                } else {
+                    print_title(&stdout);
+                }
                 return Ok(());
             }
             Err(err) => {
@@ -260,7 +402,15 @@ fn main() -> AnyResult<()> {
                 0
             };
 
-            display_animessage(TUTORIAL, false, debug, no_exec, start_index, &stdout)?;
+            display_animessage(
+                TUTORIAL,
+                false,
+                debug,
+                no_exec,
+                start_index,
+                &mut stage,
+                &mut audio,
+            )?;
         }
     } else {
         let file: PathBuf = file.unwrap();
@@ -326,14 +476,23 @@ fn main() -> AnyResult<()> {
             debug,
             no_exec,
             start_index,
-            &stdout,
+            &mut stage,
+            &mut audio,
         )?;
     }
 
+    stage.release_controls();
     if debug {
         debug!("--- END --- ");
     }
-    print_title(&stdout);
+    if let Some(export) = &export {
+        self::export(&output_log.events(), export)?;
+    }
+    if stats {
+        println!("{}", stage.stats().report());
+    } else {
+        print_title(&stdout);
+    }
 
     // if let Ok((columns, rows)) = terminal::size() {
     //     move_cursor(columns, rows);
@@ -350,8 +509,31 @@ mod tests {
     #[test]
     fn syntax_test() {
         let stdout = Term::stdout();
-        let res = display_animessage(TUTORIAL, true, true, true, 0, &stdout);
+        let mut stage = Stage::new().with_renderer(TerminalRenderer::new(stdout.clone()));
+        let mut audio = Audio::new(false);
+        let res = display_animessage(TUTORIAL, true, true, true, 0, &mut stage, &mut audio);
         stdout.show_cursor();
         assert!(res.is_ok());
     }
+
+    #[test]
+    fn tutorial_plays_instantly_with_a_virtual_clock() {
+        let screen = MemoryRenderer::new(80, 24);
+        let mut stage = Stage::new()
+            .with_headless(true)
+            .with_renderer(screen.clone());
+        let mut audio = Audio::new(false);
+        let started = std::time::Instant::now();
+        display_animessage(TUTORIAL, false, false, false, 0, &mut stage, &mut audio).unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(
+            screen.screen().title(),
+            "Animessage | Tutorial | Congratulations !"
+        );
+        assert!(screen
+            .screen()
+            .text()
+            .contains("You've reached the end of the tutorial !"));
+    }
 }
@@ -1,28 +1,43 @@
+use std::rc::Rc;
+
 use inquire::error::InquireError;
-use viuer::terminal_size;
 
 use super::*;
 
 // Functions
-pub(crate) const PRINT: &str = "--[PRINT]--"; // Prints your previous lines to the console. 1st arg : Delay between each character.
-pub(crate) const PRINT_LINE: &str = "--[PRINT_LINE]--"; // Prints your previous lines to the console with a newline at the end. 1st arg : Delay between each character.
+pub(crate) const PRINT: &str = "--[PRINT]--"; // Prints your previous lines to the console. 1st arg : Delay between each character. Optional arg : "sound=tick|path|none", the sound of each typed character.
+pub(crate) const PRINT_LINE: &str = "--[PRINT_LINE]--"; // Prints your previous lines to the console with a newline at the end. 1st arg : Delay between each character. Optional arg : Same as --[PRINT]--.
+pub(crate) const TYPEWRITER: &str = "--[TYPEWRITER]--"; // Sets the sound of each character typed by the next prints. 1st arg : "tick" for a synthesized tick, a path to an audio file, or "none".
 pub(crate) const VAR: &str = "--[VAR]--"; // BETA. DOESN'T WORK YET.
 pub(crate) const GOTO: &str = "--[GOTO]--"; // Go to a line. 1st arg : line to go to.
 pub(crate) const MARKER: &str = "--[MARKER]--"; // Sets a marker to easily go to a specified line of the animessage via the command parameter '-m'. 1st arg : Marker name. 2nd arg : line to start from.
 pub(crate) const WAIT: &str = "--[WAIT]--"; // Wait for some duration before continuing. 1st arg : Duration in ms.
 pub(crate) const REPLACE: &str = "--[REPLACE]--"; // Replace text at a given line. 1st arg : line. 2nd arg : Replace from. 3rd arg : Replace to.
 pub(crate) const DEL_LINE: &str = "--[DEL_LINE]--"; // Deletes a line, therefore offsetting the following lines by -1. 1st arg : line number.
-pub(crate) const WAIT_FOR_INPUT: &str = "--[WAIT_FOR_INPUT]--"; // Wait for a keyboard key to be input before continuing. 1st arg : Key.
+pub(crate) const WAIT_FOR_INPUT: &str = "--[WAIT_FOR_INPUT]--"; // Wait for a keyboard key to be input before continuing. 1st arg : Key, several keys separated by spaces, combinations such as "Ctrl+K", or "ANY". Optional args : "var=name" to put the key pressed into a variable, "timeout=duration" to carry on without a key, and "goto=marker" to go to a marker after the timeout.
+pub(crate) const BIND: &str = "--[BIND]--"; // Goes to a marker whenever some keys are pressed, for the rest of the animessage. 1st arg : Keys, written like those of --[WAIT_FOR_INPUT]--. 2nd arg : Marker name.
+pub(crate) const UNBIND: &str = "--[UNBIND]--"; // Removes keys bound by --[BIND]--. Optional 1st arg : Keys, written as they were bound. Every binding is removed without it.
 pub(crate) const OPEN_URL: &str = "--[OPEN_URL]--"; // Opens a given URL if the user allows it. 1st arg : URL.
-pub(crate) const AUDIO: &str = "--[AUDIO]--"; // Plays a sound in the background. 1st arg : Sound path.
-pub(crate) const TTS: &str = "--[TTS]--"; // NOT IMPLEMENTED YET. Reads some text using the default text-to-speech voice from your operating system.
+pub(crate) const AUDIO: &str = "--[AUDIO]--"; // Plays a sound in the background. 1st arg : Sound path. Optional args : "channel=name" to control the sound later, "loop=true|false", "start=position in the sound" and "volume=number or percentage".
+pub(crate) const AUDIO_STOP: &str = "--[AUDIO_STOP]--"; // Stops the sound of a channel. Optional 1st arg : Channel name. Every channel is stopped without it.
+pub(crate) const AUDIO_PAUSE: &str = "--[AUDIO_PAUSE]--"; // Pauses the sound of a channel. Optional 1st arg : Channel name. Every channel is paused without it.
+pub(crate) const AUDIO_RESUME: &str = "--[AUDIO_RESUME]--"; // Resumes the sound of a paused channel. Optional 1st arg : Channel name. Every channel is resumed without it.
+pub(crate) const AUDIO_VOLUME: &str = "--[AUDIO_VOLUME]--"; // Sets the volume of a channel. 1st arg : Volume as a number (1.0 is the file's volume) or a percentage. Optional 2nd arg : Channel name. Every channel is changed without it.
+pub(crate) const WAIT_AUDIO: &str = "--[WAIT_AUDIO]--"; // Waits until the sound of a channel has ended. Waits forever for looped sounds. 1st arg : Channel name.
+pub(crate) const AUDIO_DURATION: &str = "--[AUDIO_DURATION]--"; // Puts the duration of an audio file in seconds into a variable, which can then be used in the args of other functions by writing "$name". 1st arg : Sound path. 2nd arg : Variable name.
+pub(crate) const TONE: &str = "--[TONE]--"; // Plays a synthesized tone in the background. 1st arg : Frequency in Hz or note ("A4", "C#5"). 2nd arg : Duration. Optional args : "wave=square|sine", "channel=name", "loop=true|false" and "volume=number or percentage".
+pub(crate) const MELODY: &str = "--[MELODY]--"; // Plays synthesized notes one after the other in the background. 1st arg : Notes separated by spaces, written as "note:duration" ("R" is a rest). Optional args : Same as --[TONE]--.
+pub(crate) const TTS: &str = "--[TTS]--"; // Reads some text aloud in the background with the text-to-speech program of your system (espeak-ng, espeak or say). 1st arg : Text.
 pub(crate) const DRAW: &str = "--[DRAW]--"; // NOT IMPLEMENTED YET. Draws forms and presets and puts them in the print buffer. Will use args.
-pub(crate) const IMAGE: &str = "--[IMAGE]--"; // Transforms an image into ASCII and then prints it to the console. 1st arg : Image path.
-pub(crate) const VIDEO: &str = "--[VIDEO]--"; // NOT IMPLEMENTED YET. Transforms a video into ASCII and then prints it to the console. 1st arg : Video path.
+pub(crate) const IMAGE: &str = "--[IMAGE]--"; // Transforms an image into ASCII and then prints it to the console. 1st arg : Image path. Optional args : "width=columns", "height=rows", "position=column,row", "mode=auto|ascii|truecolor|256|16". GIFs are animated : "loops=number" (0 loops forever) and "duration=max duration".
+pub(crate) const VIDEO: &str = "--[VIDEO]--"; // Plays a video as text. 1st arg : Directory of numbered images, animated PNG or GIF. Optional args : "width=columns", "height=rows", "position=column,row", "mode=auto|ascii|truecolor|256|16", "fps=frames per second" and "audio=path to the audio track".
 pub(crate) const TITLE: &str = "--[TITLE]--"; // Sets the title of the terminal. 1st arg : title.
 pub(crate) const CLEAR: &str = "--[CLEAR]--"; // Clears the terminal, leaving the terminal empty. Often used before print to seperate steps in your animessage.
+pub(crate) const SLIDE: &str = "--[SLIDE]--"; // Starts a new slide by clearing the terminal. With --present, waits for a key first, like --[CLEAR]-- does.
 pub(crate) const RESIZE: &str = "--[RESIZE]--"; // Resizes the terminal. 1st arg : columns. 2nd arg : rows.
 pub(crate) const MOVE_CURSOR: &str = "--[MOVE_CURSOR]--"; // Moves the cursor to the specified location in columns * rows. 1st arg : columns. 2nd arg : rows.
+pub(crate) const SPRITE: &str = "--[SPRITE]--"; // Turns the lines in the print buffer into a sprite instead of printing them. 1st arg : Sprite name.
+pub(crate) const MOVE: &str = "--[MOVE]--"; // Moves a sprite across the screen while the animessage goes on. 1st arg : Sprite name. 2nd arg : Start position as "column,row". 3rd arg : End position. 4th arg : Duration. Optional 5th arg : Easing ("linear" by default, "ease-in", "ease-out" or "ease-in-out").
 pub(crate) const HIDE_CURSOR: &str = "--[HIDE_CURSOR]--"; // Hides the cursor. DUH !
 pub(crate) const SHOW_CURSOR: &str = "--[SHOW_CURSOR]--"; // Shows the cursor. DUH !
 pub(crate) const EMPTY: &str = "--[EMPTY]--"; // Inserts an empty line. By default, empty lines in your code have no effect to allow better formatting of your code.
@@ -38,7 +53,8 @@ pub fn display_animessage(
     debug: bool,
     no_exec: bool,
     start_index: usize,
-    stdout: &Term,
+    stage: &mut Stage,
+    audio: &mut Audio,
 ) -> AnyResult<()> {
     let mut current_step = String::with_capacity(1024);
     // let mut expected_steps_n: u64 = 0;
@@ -48,12 +64,13 @@ pub fn display_animessage(
     let mut replaces_cache: HashMap<usize, [String; 2]> = HashMap::new();
     let mut vars: HashMap<String, Variable> = HashMap::new();
 
-    let (_stream, stream_handle) = rodio::OutputStream::try_default().unwrap();
-
-    let mut lines: Vec<String> = animessage_str // IDEA : Change to a HashMap<usize, String> if keeping lines number/index in place becomes necessary.
-        .lines()
-        .map(|s| s.to_string())
-        .collect();
+    // Shared with the checkpoints, and only copied when a function changes them.
+    let mut lines: Rc<Vec<String>> = Rc::new(
+        animessage_str // IDEA : Change to a HashMap<usize, String> if keeping lines number/index in place becomes necessary.
+            .lines()
+            .map(|s| s.to_string())
+            .collect(),
+    );
     let _orig_lines = lines.clone();
 
     let lines_number_count = lines.len().to_string().chars().count();
@@ -64,9 +81,157 @@ pub fn display_animessage(
     //     save_cursor_position()?;
     // }
 
+    let mut history = History::default();
+    let mut step_ended = true; // Whether a checkpoint of the animessage should be kept, to go back to it.
+    let mut slide_shown = false; // Whether something has been shown since the current slide started, so that --present waits before the next one.
+    let mut slides_cache = None;
+    let mut slide_states: HashMap<usize, PlayState> = HashMap::new(); // K: slide index / V: state when it started, to go back to it.
+    if stage.presents() && !no_exec {
+        let slides = Slides::cached(&mut slides_cache, &lines);
+        show_slide_footer(stage, slides, slides.index_at(start_index));
+    }
+
     let mut line_index: usize = start_index;
-    'main_loop: while line_index + 1 <= lines.len() {
-        let line = lines[line_index].clone();
+    'main_loop: loop {
+        if std::mem::take(&mut step_ended) {
+            if let Some(screen) = stage.screen() {
+                let state = PlayState {
+                    line_index,
+                    lines: lines.clone(),
+                    vars: vars.clone(),
+                    gotos_cache: gotos_cache.clone(),
+                    replaces_cache: replaces_cache.clone(),
+                    current_step: current_step.clone(),
+                };
+                history.record(state, screen);
+            }
+        }
+        match stage.interruption() {
+            Some(Interruption::Quit) => {
+                if debug {
+                    debug!("Quitting, as asked with the playback controls.");
+                }
+                return Ok(());
+            }
+            Some(Interruption::NextMarker) => {
+                stage.clear_interruption();
+                current_step.clear();
+                slide_shown = false;
+                match lines[line_index..]
+                    .iter()
+                    .position(|line| line.trim_start().starts_with(MARKER))
+                {
+                    Some(offset) => line_index += offset,
+                    None => return Ok(()),
+                }
+                if debug {
+                    debug!("Skipping to the next marker, at line {}.", line_index + 1);
+                }
+            }
+            Some(Interruption::JumpTo(marker)) => {
+                stage.clear_interruption();
+                match find_marker(lines.iter().map(String::as_str), &marker) {
+                    Some(marker_index) => {
+                        current_step.clear();
+                        slide_shown = false;
+                        line_index = marker_index;
+                        if debug {
+                            debug!(
+                                "Going to marker {:?}, at line {}, as bound to the keys pressed.",
+                                marker,
+                                line_index + 1
+                            );
+                        }
+                    }
+                    None => warn!(
+                        "Marker {:?} doesn't exist anymore, so its keys are ignored.",
+                        marker
+                    ),
+                }
+            }
+            Some(interruption @ (Interruption::PreviousStep | Interruption::NextStep)) => {
+                stage.clear_interruption();
+                let checkpoint = match interruption {
+                    Interruption::PreviousStep => history.rewind(),
+                    _ => history.forward(), // Without one, the step it was about has been finished.
+                };
+                if let Some(checkpoint) = checkpoint {
+                    PlayState {
+                        line_index,
+                        lines,
+                        vars,
+                        gotos_cache,
+                        replaces_cache,
+                        current_step,
+                    } = checkpoint.state.clone();
+                    stage.restore_screen(&checkpoint.screen);
+                    if debug {
+                        debug!("Going to the step ending at line {}.", line_index);
+                    }
+                    continue 'main_loop;
+                }
+            }
+            Some(Interruption::FinishStep) => stage.clear_interruption(), // The step it was about has ended.
+            None => (),
+        }
+        let slide_ends = line_index >= lines.len() || is_slide_boundary(lines[line_index].trim());
+        if stage.presents() && slide_shown && slide_ends && !no_exec {
+            let slides = Slides::cached(&mut slides_cache, &lines);
+            let current = slides.index_at(line_index.saturating_sub(1));
+            slide_shown = false;
+            match wait_for_slide_key(stage, slides, current)? {
+                Navigation::Next => (),
+                Navigation::GoTo(index) => {
+                    stage.clear()?;
+                    show_slide_footer(stage, slides, index);
+                    match slide_states.get(&index) {
+                        Some(state) => {
+                            PlayState {
+                                line_index,
+                                lines,
+                                vars,
+                                gotos_cache,
+                                replaces_cache,
+                                current_step,
+                            } = state.clone();
+                        }
+                        None => {
+                            current_step.clear(); // Not reached yet, so it's played from its start with the current state.
+                            line_index = slides.start(index);
+                        }
+                    }
+                    if debug {
+                        debug!("Going to slide {}, at line {}.", index + 1, line_index + 1);
+                    }
+                    continue 'main_loop;
+                }
+                Navigation::Quit => return Ok(()),
+            }
+        }
+        if line_index >= lines.len() {
+            break;
+        }
+        if stage.presents() && !no_exec {
+            let slides = Slides::cached(&mut slides_cache, &lines);
+            let index = slides.index_at(line_index);
+            if slides.start(index) == line_index {
+                slide_states.insert(
+                    index,
+                    PlayState {
+                        line_index,
+                        lines: lines.clone(),
+                        vars: vars.clone(),
+                        gotos_cache: gotos_cache.clone(),
+                        replaces_cache: replaces_cache.clone(),
+                        current_step: current_step.clone(),
+                    },
+                );
+            }
+        }
+        let mut line = lines[line_index].clone();
+        if line.trim_start().starts_with("--[") && !line.trim_start().starts_with(ESCAPE) {
+            line = substitute_vars(&line, &vars); // Only function args can use variables.
+        }
         let line_trimmed = line.trim();
         let line_number = line_index + 1;
 
@@ -81,17 +246,26 @@ pub fn display_animessage(
 
         // PRINT
         if line_trimmed.starts_with(PRINT) {
-            let args = Args::parse(line_trimmed, 1)?;
+            let args = Args::parse_kwargs(line_trimmed, 1, &["sound"])?;
             let print_interval = duration_from_arg(args.get(0))?; // We have verified that the number of args is correct so we can index as we please.
+            let mut sound = match args.kwarg("sound") {
+                Some(sound) => TypewriterSound::from_arg(sound),
+                None => audio.typewriter().cloned(),
+            };
 
             if !current_step.is_empty() {
+                if audio.speaks_steps() && !no_exec {
+                    if let Err(err) = audio.speech().speak(current_step.trim()) {
+                        warn!("Can't read this step aloud. Error details : {}", err);
+                    }
+                }
                 if print_interval == Duration::ZERO {
                     if debug {
                         debug!("Printing this step all at once.");
                     }
                     if !no_exec {
-                        print!("{}", &current_step);
-                        flush_stdout();
+                        stage.print(&current_step);
+                        stage.flush();
                     }
                 } else {
                     if debug {
@@ -101,33 +275,66 @@ pub fn display_animessage(
                         );
                     }
                     if !no_exec {
-                        for line_string in current_step.lines() {
+                        'typing: for line_string in current_step.lines() {
                             for c in line_string.chars() {
-                                print!("{}", c);
-                                flush_stdout();
-                                sleep(print_interval);
+                                stage.print(c.encode_utf8(&mut [0; 4]));
+                                stage.flush();
+                                if let (Some(typewriter_sound), false) = (&sound, c.is_whitespace())
+                                {
+                                    if let Err(err) =
+                                        audio.type_character(typewriter_sound, stage.now())
+                                    {
+                                        warn!("Can't play the typewriter sound, so this step is typed silently. Error details : {}", err);
+                                        sound = None;
+                                    }
+                                }
+                                stage.sleep(print_interval);
+                                if leaves_step(stage, &history) {
+                                    break 'typing;
+                                }
                             }
                         }
                     }
                 }
 
+                if no_exec {
+                    let typed_chars: usize =
+                        current_step.lines().map(|line| line.chars().count()).sum();
+                    let stats = stage.stats_mut();
+                    stats.steps += 1;
+                    stats.runtime += print_interval * typed_chars as u32;
+                } else if !leaves_step(stage, &history) {
+                    stage.mark(&format!("PRINT line {}", line_number));
+                    step_ended = true;
+                    slide_shown = true;
+                }
                 current_step.clear();
                 if debug {
                     debug!("Current print buffer has been cleared.");
                 }
             }
         } else if line_trimmed.starts_with(PRINT_LINE) {
-            let args = Args::parse(line_trimmed, 1)?;
+            let args = Args::parse_kwargs(line_trimmed, 1, &["sound"])?;
             let print_interval = duration_from_arg(args.get(0))?; // We have verified that the number of args is correct so we can index as we please.
+            let mut sound = match args.kwarg("sound") {
+                Some(sound) => TypewriterSound::from_arg(sound),
+                None => audio.typewriter().cloned(),
+            };
 
             if !current_step.is_empty() {
+                if audio.speaks_steps() && !no_exec {
+                    if let Err(err) = audio.speech().speak(current_step.trim()) {
+                        warn!("Can't read this step aloud. Error details : {}", err);
+                    }
+                }
                 if print_interval == Duration::ZERO {
                     if debug {
                         debug!("Printing this step all at once.");
                     }
                     if !no_exec {
-                        println!("{}", &current_step);
-                        flush_stdout();
+                        stage.print(&current_step);
+                        stage.print("\n");
+                        stage.flush();
                     }
                 } else {
                     if debug {
@@ -137,22 +344,57 @@ pub fn display_animessage(
                         );
                     }
                     if !no_exec {
-                        for line_string in current_step.lines() {
+                        'typing: for line_string in current_step.lines() {
                             for c in line_string.chars() {
-                                print!("{}", c);
-                                flush_stdout();
-                                sleep(print_interval);
+                                stage.print(c.encode_utf8(&mut [0; 4]));
+                                stage.flush();
+                                if let (Some(typewriter_sound), false) = (&sound, c.is_whitespace())
+                                {
+                                    if let Err(err) =
+                                        audio.type_character(typewriter_sound, stage.now())
+                                    {
+                                        warn!("Can't play the typewriter sound, so this step is typed silently. Error details : {}", err);
+                                        sound = None;
+                                    }
+                                }
+                                stage.sleep(print_interval);
+                                if leaves_step(stage, &history) {
+                                    break 'typing;
+                                }
                             }
-                            println!();
+                            stage.print("\n");
+                            stage.flush();
                         }
                     }
                 }
 
+                if no_exec {
+                    let typed_chars: usize =
+                        current_step.lines().map(|line| line.chars().count()).sum();
+                    let stats = stage.stats_mut();
+                    stats.steps += 1;
+                    stats.runtime += print_interval * typed_chars as u32;
+                } else if !leaves_step(stage, &history) {
+                    stage.mark(&format!("PRINT line {}", line_number));
+                    step_ended = true;
+                    slide_shown = true;
+                }
                 current_step.clear();
                 if debug {
                     debug!("Current print buffer has been cleared.");
                 }
             }
+        } else if line_trimmed.starts_with(TYPEWRITER) {
+            let args = Args::parse(line_trimmed, 1)?;
+            let sound = TypewriterSound::from_arg(args.get(0));
+            if let Some(TypewriterSound::File(path)) = &sound {
+                check_relative_path_ok(path, relative_paths_ok);
+            }
+
+            if debug {
+                debug!("Typewriter sound set to {:?}", sound);
+            }
+            audio.set_typewriter(sound);
         } else if line_trimmed.starts_with(VAR) {
             // TODO: Changer la fonction pour aussi prendre en charge le mode GET.
             error!("UNSTABLE FUNCTION. Do not use this function.");
@@ -186,6 +428,9 @@ pub fn display_animessage(
                 }
             };
 
+            if no_exec && goto_line_number <= line_number {
+                stage.stats_mut().loop_pass(line_number, goto_line_number);
+            }
             if gotos_cache.contains_key(&line_number) {
                 if debug {
                     debug!(
@@ -204,15 +449,20 @@ pub fn display_animessage(
         } else if line_trimmed.starts_with(WAIT) {
             let args = Args::parse(line_trimmed, 1)?;
             let wait_time_str = args.get(0);
+            if no_exec && wait_time_str.contains('$') {
+                stage.stats_mut().untimed_delays.push(line_number); // Its variable is only set while playing.
+            } else {
+                let duration = duration_from_arg(&wait_time_str)?;
 
-            let duration = duration_from_arg(&wait_time_str)?;
-
-            if debug {
-                debug!("Waiting for {:?} before continuing...", &duration);
-            }
+                if debug {
+                    debug!("Waiting for {:?} before continuing...", &duration);
+                }
 
-            if !no_exec {
-                sleep(duration);
+                if no_exec {
+                    stage.stats_mut().runtime += duration;
+                } else {
+                    stage.sleep(duration);
+                }
             }
         } else if line_trimmed.starts_with(REPLACE) {
             let args = Args::parse(line_trimmed, 3)?;
@@ -239,7 +489,8 @@ pub fn display_animessage(
                     );
                 }
                 replaces_cache.insert(line_to_modify, array_replace);
-                lines[line_to_modify] = lines[line_to_modify].replace(replace_from, replace_with);
+                Rc::make_mut(&mut lines)[line_to_modify] =
+                    lines[line_to_modify].replace(replace_from, replace_with);
             } else {
                 if debug {
                     debug!("Not replacing text at line {:?} : text has already been replaced with the same arguments.", &line_replace_number);
@@ -257,41 +508,81 @@ pub fn display_animessage(
             };
             del_line_index -= 1; // Now it's actually a line index.
 
-            lines.remove(del_line_index);
+            Rc::make_mut(&mut lines).remove(del_line_index);
 
             if debug {
                 debug!("Deleted line {}", del_line_number_str)
             }
         } else if line_trimmed.starts_with(WAIT_FOR_INPUT) {
-            let args = Args::parse(line_trimmed, 1)?;
-            let mut expected_key = args.get(0).to_string();
-
-            if expected_key.chars().count() == 1 {
-                expected_key = expected_key.to_uppercase();
-            } else {
-                expected_key = expected_key.to_string();
-            }
-
-            if !no_exec {
-                sleep(Duration::from_millis(250)); // To avoid chaining events unwillingly if expected_key is pressed for too long.
-            }
+            let args = Args::parse_kwargs(line_trimmed, 1, &["var", "timeout", "goto"])?;
+            let expected_keys = match ExpectedKeys::from_str(args.get(0)) {
+                Ok(expected_keys) => expected_keys,
+                Err(err) => {
+                    error!("{}", err);
+                    return Ok(());
+                }
+            };
+            let timeout = args.kwarg("timeout").map(duration_from_arg).transpose()?;
+            let timeout_marker_index = match (args.kwarg("goto"), timeout) {
+                (Some(marker), Some(_)) => {
+                    match find_marker(lines.iter().map(String::as_str), marker) {
+                        Some(index) => Some(index),
+                        None => {
+                            error!("No marker named {:?} to go to after the timeout of this --[WAIT_FOR_INPUT]-- .", marker);
+                            return Ok(());
+                        }
+                    }
+                }
+                (Some(_), None) => {
+                    error!("The \"goto\" arg of --[WAIT_FOR_INPUT]-- is the marker to go to after its timeout, so a \"timeout\" arg is needed too.");
+                    return Ok(());
+                }
+                (None, _) => None,
+            };
 
             if debug {
-                debug!("Expecting key {:?} ...\n", &expected_key);
+                debug!("Expecting key {:?} ...\n", args.get(0));
             }
 
-            if !no_exec {
-                let device_state = DeviceState::new();
+            let mut pressed_key = None;
+            let mut timed_out = false;
+            if no_exec {
+                match timeout {
+                    Some(timeout) => stage.stats_mut().runtime += timeout, // At most.
+                    None => stage.stats_mut().key_waits.push(line_number),
+                }
+            }
+            if !no_exec && stage.is_headless() {
+                if let Some(timeout) = timeout {
+                    stage.sleep(timeout);
+                    timed_out = true;
+                }
+                if debug {
+                    debug!("Not waiting for a key in headless mode.");
+                }
+            } else if !no_exec {
+                let started = stage.now();
+                stage.suspend_controls(); // The expected key could be one of them.
+                stage.poll_sleep(Duration::from_millis(250)); // To avoid chaining events unwillingly if expected_key is pressed for too long.
+                let mut key_reader = match stage.key_reader() {
+                    Ok(key_reader) => Some(key_reader),
+                    Err(err) => {
+                        warn!("Can't read the keys of the terminal, so key {:?} isn't waited for. Error details : {}", args.get(0), err);
+                        None
+                    }
+                };
                 let mut del_last_line = false;
                 let mut last_dbg_msg = String::new();
-                'key_loop: loop {
-                    let keys = device_state.get_keys();
+                'key_loop: while let Some(key_reader) = &mut key_reader {
+                    let keys = key_reader.pressed_keys()?;
                     if debug {
                         let dbg_msg = format!("Keys pressed : {:?}", &keys);
                         let dbg_msg_lines_count = dbg_msg.lines().count();
-                        if del_last_line && dbg_msg != last_dbg_msg {
-                            move_to_previous_line(stdout, dbg_msg_lines_count)?;
-                            let cols = match stdout.size_checked() {
+                        if let (Some(term), true) =
+                            (stage.term(), del_last_line && dbg_msg != last_dbg_msg)
+                        {
+                            move_to_previous_line(term, dbg_msg_lines_count)?;
+                            let cols = match term.size_checked() {
                                 Some((_rows, cols)) => cols as usize,
                                 None => 68,
                             };
@@ -300,7 +591,7 @@ pub fn display_animessage(
                                 erasing_line.push(' ');
                             }
                             println!("{}", erasing_line);
-                            move_to_previous_line(stdout, dbg_msg_lines_count)?;
+                            move_to_previous_line(term, dbg_msg_lines_count)?;
                             debug!("{}", dbg_msg);
                             last_dbg_msg = dbg_msg;
                         }
@@ -308,27 +599,84 @@ pub fn display_animessage(
                             del_last_line = true;
                         }
                     }
-                    if !keys.is_empty() {
-                        let expected_key_clone = expected_key.clone();
-                        let keycode_from_str = match Keycode::from_str(&expected_key_clone) {
-                            Ok(key) => key,
-                            Err(_) => {
-                                error!("Key {:?} isn't supported or isn't a correct key. Please replace the key in your animessage with an alphanumeric key, or a special common key (such as LControl for example) instead.", &expected_key_clone);
-                                return Ok(());
-                            }
-                        };
-                        if keys.contains(&keycode_from_str) {
-                            break 'key_loop;
-                        }
+                    pressed_key = expected_keys.pressed(&keys);
+                    if pressed_key.is_some() {
+                        break 'key_loop;
+                    }
+                    if let Some(marker) = stage.bound_marker(&keys) {
+                        stage.interrupt(Interruption::JumpTo(marker));
+                        break 'key_loop;
                     }
-                    sleep(Duration::from_millis(10));
+                    if keys.contains(&Keycode::C) && keys.contains(&Keycode::LControl) {
+                        stage.interrupt(Interruption::Quit); // Ctrl + C doesn't stop Animessage by itself in raw mode.
+                        break 'key_loop;
+                    }
+                    // Timeouts are delays, so they're skipped with the delays.
+                    timed_out = timeout
+                        .is_some_and(|timeout| !stage.waits() || stage.now() - started >= timeout);
+                    if timed_out {
+                        break 'key_loop;
+                    }
+                    stage.poll_sleep(Duration::from_millis(10));
                 }
+                drop(key_reader);
+                stage.resume_controls();
                 if debug {
-                    debug!(
-                        "Key {:?} triggered this --[WAIT_FOR_INPUT]-- .",
-                        &expected_key
-                    );
+                    match &pressed_key {
+                        Some(key) => debug!("Key {:?} triggered this --[WAIT_FOR_INPUT]-- .", key),
+                        None if timed_out => debug!("No key has been pressed before the timeout of this --[WAIT_FOR_INPUT]-- ."),
+                        None => (),
+                    }
+                }
+            }
+            if !no_exec {
+                if let Some(var_name) = args.kwarg("var") {
+                    vars.insert(
+                        var_name.to_string(),
+                        Variable::String(pressed_key.unwrap_or_default()),
+                    ); // Empty after a timeout.
+                }
+                if let (true, Some(marker_index)) = (timed_out, timeout_marker_index) {
+                    line_index = marker_index;
+                    continue 'main_loop;
+                }
+            }
+        } else if line_trimmed.starts_with(BIND) {
+            let args = Args::parse(line_trimmed, 2)?;
+            let keys = match ExpectedKeys::from_str(args.get(0)) {
+                Ok(keys) => keys,
+                Err(err) => {
+                    error!("{}", err);
+                    return Ok(());
                 }
+            };
+            let marker = args.get(1);
+            if find_marker(lines.iter().map(String::as_str), marker).is_none() {
+                error!(
+                    "No marker named {:?} to bind keys {:?} to.",
+                    marker,
+                    args.get(0)
+                );
+                return Ok(());
+            }
+
+            if debug {
+                debug!("Binding keys {:?} to marker {:?}.", args.get(0), marker);
+            }
+
+            if !no_exec {
+                stage.bind(args.get(0), keys, marker);
+            }
+        } else if line_trimmed.starts_with(UNBIND) {
+            let args = Args::parse_optional(line_trimmed, 1, 1)?;
+            let keys = args.get_opt(0);
+
+            if debug {
+                debug!("Unbinding keys {:?}.", keys.unwrap_or("(all)"));
+            }
+
+            if !no_exec {
+                stage.unbind(keys);
             }
         } else if line_trimmed.starts_with(OPEN_URL) {
             let args = Args::parse(line_trimmed, 1)?;
@@ -339,45 +687,55 @@ pub fn display_animessage(
                 return Ok(());
             }
 
-            if !no_exec {
+            if no_exec && !url.is_empty() {
+                stage.stats_mut().prompts.push(line_number);
+            } else if !no_exec {
                 if !url.is_empty() {
                     if debug {
                         debug!("Prompting user to open URL {url:?} ...");
                     }
-                    println!();
-                    let prompt_msg = format!(
-                        "Open the following URL with your default internet browser ? {}",
-                        url
-                    );
-                    let yes = Confirm::new(&prompt_msg)
-                        .with_help_message(
-                            "Type \"y\" to accept or \"n\" to refuse, and then press \"Enter\".",
-                        )
-                        .prompt();
-                    match yes {
-                        Ok(true) => {
-                            let webbrowser_result = webbrowser::open(&url);
-                            if debug {
-                                match webbrowser_result {
-                                    Ok(()) => debug!("Successfully opened URL {:?}.", &url),
-                                    Err(err) => warn!(
-                                        "URL has not been opened {:?}. Error details :\n{:#?}",
-                                        &url, &err
-                                    ),
+                    if stage.is_headless() {
+                        if debug {
+                            debug!("Not opening URL {:?} in headless mode.", &url);
+                        }
+                    } else {
+                        println!();
+                        let prompt_msg = format!(
+                            "Open the following URL with your default internet browser ? {}",
+                            url
+                        );
+                        stage.suspend_controls();
+                        let yes = Confirm::new(&prompt_msg)
+                            .with_help_message(
+                                "Type \"y\" to accept or \"n\" to refuse, and then press \"Enter\".",
+                            )
+                            .prompt();
+                        stage.resume_controls();
+                        match yes {
+                            Ok(true) => {
+                                let webbrowser_result = webbrowser::open(&url);
+                                if debug {
+                                    match webbrowser_result {
+                                        Ok(()) => debug!("Successfully opened URL {:?}.", &url),
+                                        Err(err) => warn!(
+                                            "URL has not been opened {:?}. Error details :\n{:#?}",
+                                            &url, &err
+                                        ),
+                                    }
                                 }
                             }
-                        }
-                        Ok(false) => {
-                            if debug {
-                                debug!("Refused opening URL {:?}.", &url);
+                            Ok(false) => {
+                                if debug {
+                                    debug!("Refused opening URL {:?}.", &url);
+                                }
                             }
-                        }
-                        Err(InquireError::OperationCanceled) => {
-                            if debug {
-                                debug!("Ignored opening URL {:?}.", &url);
+                            Err(InquireError::OperationCanceled) => {
+                                if debug {
+                                    debug!("Ignored opening URL {:?}.", &url);
+                                }
                             }
+                            Err(_) => (),
                         }
-                        Err(_) => (),
                     }
                 } else {
                     error!("URL is empty. Please enter an URL as the 1st argument.");
@@ -385,8 +743,30 @@ pub fn display_animessage(
                 }
             }
         } else if line_trimmed.starts_with(AUDIO) {
-            let args = Args::parse(line_trimmed, 1)?;
+            let args =
+                Args::parse_kwargs(line_trimmed, 1, &["channel", "loop", "start", "volume"])?;
             let audio_path: PathBuf = args.get(0).into();
+            let options = AudioOptions {
+                channel: args.kwarg("channel").map(String::from),
+                looped: match args.kwarg("loop").map(str::parse::<bool>) {
+                    Some(Ok(looped)) => looped,
+                    Some(Err(_err)) => {
+                        error!("Can't convert \"loop\" into true or false.");
+                        return Ok(());
+                    }
+                    None => false,
+                },
+                start: args
+                    .kwarg("start")
+                    .map(duration_from_arg)
+                    .transpose()?
+                    .unwrap_or(Duration::ZERO),
+                volume: args
+                    .kwarg("volume")
+                    .map(volume_from_arg)
+                    .transpose()?
+                    .unwrap_or(1.0),
+            };
 
             if !audio_path.as_os_str().is_empty() {
                 check_relative_path_ok(&audio_path, relative_paths_ok);
@@ -395,33 +775,11 @@ pub fn display_animessage(
                     debug!("Playing audio file {:?} ...", &audio_path);
                 }
 
-                match File::open(&audio_path) {
-                    Ok(file) => {
-                        let buf_reader = BufReader::new(file);
-                        match rodio::Decoder::new(buf_reader) {
-                            Ok(source) => {
-                                if !no_exec {
-                                    if let Err(err) =
-                                        stream_handle.play_raw(source.convert_samples())
-                                    {
-                                        warn!("Can't read audio using your default output device (details below) :\n{:#?}", err)
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                error!(
-                                    "AUDIO ERROR : Can't read audio from file {:?} . Error : \n{}",
-                                    &audio_path,
-                                    e.to_string()
-                                );
-                                return Ok(());
-                            }
-                        }
-                    }
-                    Err(e) => {
+                if !no_exec {
+                    if let Err(err) = audio.backend().play(&audio_path, &options) {
                         error!(
-                            "FILE ERROR : Can't open audio file. Error : \n{}",
-                            e.to_string()
+                            "AUDIO ERROR : Can't play audio file {:?} . Error : \n{}",
+                            &audio_path, err
                         );
                         return Ok(());
                     }
@@ -430,64 +788,290 @@ pub fn display_animessage(
                 error!("ARG ERROR : Please specify a path as 1st argument of --[AUDIO]-- :\n--[AUDIO]-- path/to/file.mp3");
                 return Ok(());
             }
-        } else if line_trimmed.starts_with(IMAGE) {
+        } else if line_trimmed.starts_with(AUDIO_STOP)
+            || line_trimmed.starts_with(AUDIO_PAUSE)
+            || line_trimmed.starts_with(AUDIO_RESUME)
+        {
+            let args = Args::parse_optional(line_trimmed, 1, 1)?;
+            let channel = args.get_opt(0);
+
+            if debug {
+                debug!(
+                    "{} on channel {:?}",
+                    line_trimmed,
+                    channel.unwrap_or("(all)")
+                );
+            }
+
+            if !no_exec {
+                let res = if line_trimmed.starts_with(AUDIO_STOP) {
+                    audio.backend().stop(channel)
+                } else if line_trimmed.starts_with(AUDIO_PAUSE) {
+                    audio.backend().pause(channel)
+                } else {
+                    audio.backend().resume(channel)
+                };
+                if let Err(err) = res {
+                    error!("AUDIO ERROR : {}", err);
+                    return Ok(());
+                }
+            }
+        } else if line_trimmed.starts_with(AUDIO_VOLUME) {
+            let args = Args::parse_optional(line_trimmed, 2, 1)?;
+            let volume = volume_from_arg(args.get(0))?;
+            let channel = args.get_opt(1);
+
+            if debug {
+                debug!(
+                    "Setting volume of channel {:?} to {}",
+                    channel.unwrap_or("(all)"),
+                    volume
+                );
+            }
+
+            if !no_exec {
+                if let Err(err) = audio.backend().set_volume(channel, volume) {
+                    error!("AUDIO ERROR : {}", err);
+                    return Ok(());
+                }
+            }
+        } else if line_trimmed.starts_with(WAIT_AUDIO) {
+            let args = Args::parse(line_trimmed, 1)?;
+            let channel = args.get(0);
+
+            if debug {
+                debug!("Waiting for the end of audio channel {:?}...", channel);
+            }
+
+            if no_exec {
+                stage.stats_mut().sound_waits.push(line_number);
+            } else {
+                audio.wait_for_channel(channel, stage);
+            }
+        } else if line_trimmed.starts_with(AUDIO_DURATION) {
+            let args = Args::parse(line_trimmed, 2)?;
+            let audio_path: PathBuf = args.get(0).into();
+            let var_name = args.get(1);
+            check_relative_path_ok(&audio_path, relative_paths_ok);
+
+            if !no_exec {
+                let duration = match audio_duration(&audio_path) {
+                    Ok(duration) => duration,
+                    Err(err) => {
+                        error!(
+                            "AUDIO ERROR : Can't read the duration of audio file {:?} . Error : \n{}",
+                            &audio_path, err
+                        );
+                        return Ok(());
+                    }
+                };
+                if debug {
+                    debug!(
+                        "${} = {:?} (duration of {:?})",
+                        var_name, duration, &audio_path
+                    );
+                }
+                vars.insert(
+                    var_name.to_string(),
+                    Variable::Float(duration.as_secs_f64()),
+                );
+            }
+        } else if line_trimmed.starts_with(TONE) || line_trimmed.starts_with(MELODY) {
+            let is_tone = line_trimmed.starts_with(TONE);
+            let args = Args::parse_kwargs(
+                line_trimmed,
+                if is_tone { 2 } else { 1 },
+                &["wave", "channel", "loop", "volume"],
+            )?;
+            let tones = if is_tone {
+                vec![Tone {
+                    frequency: Some(frequency_from_arg(args.get(0))?),
+                    duration: duration_from_arg(args.get(1))?,
+                }]
+            } else {
+                match parse_melody(args.get(0)) {
+                    Ok(tones) => tones,
+                    Err(err) => {
+                        error!("ARG ERROR : {}", err);
+                        return Ok(());
+                    }
+                }
+            };
+            let wave = args
+                .kwarg("wave")
+                .map(Wave::from_str)
+                .transpose()?
+                .unwrap_or(Wave::Square);
+            let options = AudioOptions {
+                channel: args.kwarg("channel").map(String::from),
+                looped: match args.kwarg("loop").map(str::parse::<bool>) {
+                    Some(Ok(looped)) => looped,
+                    Some(Err(_err)) => {
+                        error!("Can't convert \"loop\" into true or false.");
+                        return Ok(());
+                    }
+                    None => false,
+                },
+                volume: args
+                    .kwarg("volume")
+                    .map(volume_from_arg)
+                    .transpose()?
+                    .unwrap_or(1.0),
+                ..AudioOptions::default()
+            };
+
+            if debug {
+                debug!("Playing {} tone(s) with a {:?} wave.", tones.len(), wave);
+            }
+
+            if !no_exec {
+                let tones = Tones::new(tones, wave, options.looped);
+                if let Err(err) = audio.backend().play_tones(tones, &options) {
+                    error!("AUDIO ERROR : Can't play tones. Error : \n{}", err);
+                    return Ok(());
+                }
+            }
+        } else if line_trimmed.starts_with(TTS) {
             let args = Args::parse(line_trimmed, 1)?;
+            let text = args.get(0);
+
+            if debug {
+                debug!("Reading {:?} aloud.", text);
+            }
+
+            if !no_exec {
+                if let Err(err) = audio.speech().speak(text) {
+                    error!("TTS ERROR : Can't read text aloud. Error : \n{}", err);
+                    return Ok(());
+                }
+            }
+        } else if line_trimmed.starts_with(IMAGE) {
+            let args = Args::parse_kwargs(
+                line_trimmed,
+                1,
+                &["width", "height", "position", "mode", "loops", "duration"],
+            )?;
             let image_path: PathBuf = args.get(0).into();
+            let options = ImageOptions {
+                width: args.kwarg("width").map(size_from_arg).transpose()?,
+                height: args.kwarg("height").map(size_from_arg).transpose()?,
+                position: args.kwarg("position").map(position_from_arg).transpose()?,
+                mode: args
+                    .kwarg("mode")
+                    .map(ImageMode::from_str)
+                    .transpose()?
+                    .unwrap_or(ImageMode::Auto),
+                loops: match args.kwarg("loops") {
+                    Some(loops) => match loops.parse::<u32>() {
+                        Ok(loops) => loops,
+                        Err(_err) => {
+                            error!("Can't convert \"loops\" into zero or a positive integer.");
+                            return Ok(());
+                        }
+                    },
+                    None => 1,
+                },
+                max_duration: args.kwarg("duration").map(duration_from_arg).transpose()?,
+            };
 
             if !image_path.as_os_str().is_empty() {
                 check_relative_path_ok(&image_path, relative_paths_ok);
                 if debug {
                     debug!("Converting image for the terminal : {:?} ...", &image_path);
                 }
-                if !no_exec {
-                    let (x, y) = match cursor::position() {
-                        Ok(pos) => pos,
-                        Err(err) => {
-                            error!("Can't obtain cursor position : {:?}", err);
-                            return Ok(());
+                if no_exec {
+                    if is_gif(&image_path) {
+                        match options.max_duration {
+                            Some(duration) => stage.stats_mut().runtime += duration, // At most.
+                            None => stage.stats_mut().untimed_delays.push(line_number),
                         }
-                    };
-
-                    let conf = viuer::Config {
-                        x,
-                        y: y as i16,
-                        width: None,
-                        height: None,
-                        use_kitty: true,
-                        use_iterm: true,
-                        // use_sixel: false,
-                        ..Default::default()
-                    };
-                    if let Err(err) = viuer::print_from_file(image_path, &conf) {
+                    }
+                } else {
+                    if let Err(err) = print_image(stage, &image_path, &options) {
                         error!("Printing image failed : {:?}", err);
                         return Ok(());
                     }
+                    slide_shown = true;
+                }
+            } else {
+                error!("ARG ERROR : Please specify a path as 1st argument of --[IMAGE]-- :\n--[IMAGE]-- path/to/file.jpg");
+                return Ok(());
+            }
+        } else if line_trimmed.starts_with(VIDEO) {
+            let args = Args::parse_kwargs(
+                line_trimmed,
+                1,
+                &["width", "height", "position", "mode", "fps", "audio"],
+            )?;
+            let video_path: PathBuf = args.get(0).into();
+            let options = VideoOptions {
+                width: args.kwarg("width").map(size_from_arg).transpose()?,
+                height: args.kwarg("height").map(size_from_arg).transpose()?,
+                position: args.kwarg("position").map(position_from_arg).transpose()?,
+                mode: args
+                    .kwarg("mode")
+                    .map(ImageMode::from_str)
+                    .transpose()?
+                    .unwrap_or(ImageMode::Auto),
+                fps: match args.kwarg("fps") {
+                    Some(fps) => match fps.parse::<f64>() {
+                        Ok(fps) if fps > 0.0 && fps.is_finite() => Some(fps),
+                        _ => {
+                            error!("Can't convert \"fps\" into a positive number.");
+                            return Ok(());
+                        }
+                    },
+                    None => None,
+                },
+                audio: args.kwarg("audio").map(PathBuf::from),
+            };
 
-                    // crossterm::execute!(
-                    //     stdout(),
-                    //     crossterm::style::SetAttribute(crossterm::style::Attribute::Reset)
-                    // )
-                    // .unwrap();
+            if !video_path.as_os_str().is_empty() {
+                check_relative_path_ok(&video_path, relative_paths_ok);
+                if let Some(audio_path) = &options.audio {
+                    check_relative_path_ok(audio_path, relative_paths_ok);
+                }
+                if debug {
+                    debug!("Playing video {:?} ...", &video_path);
+                }
+                if no_exec {
+                    match frames_duration(&video_path, options.fps) {
+                        Some(duration) => stage.stats_mut().runtime += duration,
+                        None => stage.stats_mut().untimed_delays.push(line_number),
+                    }
+                } else {
+                    if let Err(err) = play_video(stage, &video_path, &options, audio) {
+                        error!("Playing video failed : {:?}", err);
+                        return Ok(());
+                    }
+                    slide_shown = true;
                 }
             } else {
-                error!("ARG ERROR : Please specify a path as 1st argument of --[ASCII_IMAGE]-- :\n--[ASCII_IMAGE]-- path/to/file.jpg");
+                error!("ARG ERROR : Please specify a path as 1st argument of --[VIDEO]-- :\n--[VIDEO]-- path/to/frames");
                 return Ok(());
             }
         } else if line_trimmed.starts_with(TITLE) {
             let args = Args::parse(line_trimmed, 1)?;
             let title = args.get(0);
 
-            stdout.set_title(&title);
+            if !no_exec {
+                stage.set_title(title);
+            }
 
             if debug {
                 debug!("Terminal title set to {:?}", title);
             }
-        } else if line_trimmed == CLEAR {
+        } else if is_slide_boundary(line_trimmed) {
             let _ = Args::parse(line_trimmed, 0);
             if debug {
                 debug!("Clearing terminal. This function has no effect in debug mode.");
             } else {
-                clear_terminal(stdout)?
+                stage.clear()?;
+                step_ended = true;
+                if stage.presents() && !no_exec {
+                    let slides = Slides::cached(&mut slides_cache, &lines);
+                    show_slide_footer(stage, slides, slides.index_at(line_index));
+                }
             }
         } else if line_trimmed.starts_with(RESIZE) {
             let args = Args::parse(line_trimmed, 2)?;
@@ -508,25 +1092,19 @@ pub fn display_animessage(
             };
 
             if debug {
-                let current_terminal_size_string =
-                    if let Some(current_terminal_size) = stdout.size_checked() {
-                        format!("{:?}", current_terminal_size)
-                    } else {
-                        "<UNKNOWN>".to_string()
-                    };
+                let current_terminal_size_string = if let Some(current_terminal_size) =
+                    stage.term().and_then(|term| term.size_checked())
+                {
+                    format!("{:?}", current_terminal_size)
+                } else {
+                    "<UNKNOWN>".to_string()
+                };
                 let new_terminal_size = (columns, rows);
                 debug!("Resizing the terminal from {} to {:?} (columns, rows). This function has no effect in debug mode.", current_terminal_size_string, new_terminal_size);
             }
 
             if !no_exec {
-                if let Err(_err) =
-                    crossterm::execute!(io::stdout(), terminal::SetSize(columns, rows))
-                {
-                    error!(
-                        "Can't resize this terminal. Use another terminal such as Windows Terminal or Alacritty."
-                    );
-                    return Ok(());
-                };
+                stage.resize(columns, rows);
             }
         } else if line_trimmed.starts_with(MOVE_CURSOR) {
             let args = Args::parse(line_trimmed, 2)?;
@@ -554,16 +1132,45 @@ pub fn display_animessage(
             }
 
             if !debug {
-                move_cursor(stdout, columns, rows)?;
+                stage.move_cursor(columns, rows)?;
+            }
+        } else if line_trimmed.starts_with(SPRITE) {
+            let args = Args::parse(line_trimmed, 1)?;
+            let sprite_name = args.get(0);
+
+            if debug {
+                debug!(
+                    "Defining sprite {:?} from the print buffer. The print buffer has been cleared.",
+                    sprite_name
+                );
+            }
+
+            stage.define_sprite(sprite_name, &current_step);
+            current_step.clear();
+        } else if line_trimmed.starts_with(MOVE) {
+            let args = Args::parse_optional(line_trimmed, 5, 1)?;
+            let sprite_name = args.get(0);
+            let from = position_from_arg(args.get(1))?;
+            let to = position_from_arg(args.get(2))?;
+            let duration = duration_from_arg(args.get(3))?;
+            let easing = match args.get_opt(4) {
+                Some(easing) => Easing::from_str(easing)?,
+                None => Easing::Linear,
+            };
+
+            if debug {
+                debug!(
+                    "Moving sprite {:?} from {:?} to {:?} (columns, rows) in {:?} with easing {:?}.",
+                    sprite_name, from, to, duration, easing
+                );
+            }
+
+            if !no_exec {
+                stage.move_sprite(sprite_name, from, to, duration, easing)?;
             }
         } else if line_trimmed == HIDE_CURSOR {
             if !no_exec {
-                if let Err(_err) = stdout.hide_cursor() {
-                    error!(
-                            "Can't resize this terminal. Use another terminal such as Windows Terminal or Alacritty."
-                        );
-                    return Ok(());
-                }
+                stage.show_cursor(false);
             }
 
             if debug {
@@ -571,12 +1178,7 @@ pub fn display_animessage(
             }
         } else if line_trimmed == SHOW_CURSOR {
             if !no_exec {
-                if let Err(_err) = stdout.show_cursor() {
-                    error!(
-                            "Can't resize this terminal. Use another terminal such as Windows Terminal or Alacritty."
-                        );
-                    return Ok(());
-                }
+                stage.show_cursor(true);
             }
 
             if debug {
@@ -596,6 +1198,7 @@ pub fn display_animessage(
                         }
 
                         let mut base_index = line_index;
+                        let lines = Rc::make_mut(&mut lines);
                         lines.remove(base_index); // Remove the --[INCLUDE]-- line.
                         for l in s.lines() {
                             // Replace the old --[INCLUDE]-- line with text from file.
@@ -623,10 +1226,11 @@ pub fn display_animessage(
             current_step.push_str(&escaped_string);
         } else if line_trimmed == EMPTY {
             current_step.push_str("\n")
-        } else if line_trimmed.is_empty()
-            || line_trimmed.starts_with(MARKER)
-            || line_trimmed.starts_with(NOTE)
-        {
+        } else if line_trimmed.starts_with(MARKER) {
+            if let (false, Some(name)) = (no_exec, marker_name(line_trimmed)) {
+                stage.mark(&format!("MARKER {}", name));
+            }
+        } else if line_trimmed.is_empty() || line_trimmed.starts_with(NOTE) {
             ()
         } else if line_trimmed == EXIT {
             return Ok(());
@@ -640,3 +1244,27 @@ pub fn display_animessage(
 
     Ok(())
 }
+
+// Whether the viewer has asked to leave the step being played, which then doesn't end.
+fn leaves_step(stage: &Stage, history: &History) -> bool {
+    stage.is_stopping()
+        || (stage.interruption() == Some(Interruption::NextStep) && history.can_go_forward())
+}
+
+/// Name of the marker set by this line, which is its 1st arg. The other args are ignored here.
+pub(crate) fn marker_name(line_trimmed: &str) -> Option<&str> {
+    if !line_trimmed.starts_with(MARKER) {
+        return None;
+    }
+    line_trimmed.split('"').nth(1) // Same as the 1st arg read by `Args`, which would fail on the other args.
+}
+
+/// Index of the line of the first marker named `name`. Markers can be indented, like every function.
+pub(crate) fn find_marker<'a>(
+    lines: impl IntoIterator<Item = &'a str>,
+    name: &str,
+) -> Option<usize> {
+    lines
+        .into_iter()
+        .position(|line| marker_name(line.trim()) == Some(name))
+}
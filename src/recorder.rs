@@ -0,0 +1,133 @@
+use std::{
+    fmt::Write as _,
+    io::BufWriter,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::*;
+
+/// Writes what the stage prints to an asciinema v2 file (https://docs.asciinema.org/manual/asciicast/v2/).
+pub struct CastRecorder {
+    file: BufWriter<File>,
+}
+
+impl CastRecorder {
+    pub fn create(path: &Path, columns: u16, rows: u16) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        writeln!(
+            file,
+            r#"{{"version": 2, "width": {}, "height": {}, "timestamp": {}, "env": {{"TERM": "xterm-256color"}}}}"#,
+            columns, rows, timestamp
+        )?;
+        Ok(CastRecorder { file })
+    }
+}
+
+impl Renderer for CastRecorder {
+    fn render(&mut self, time: Duration, output: &str) -> io::Result<()> {
+        writeln!(
+            self.file,
+            "[{:.6}, \"o\", {}]",
+            time.as_secs_f64(),
+            json_string(output)
+        )
+    }
+
+    fn mark(&mut self, time: Duration, label: &str) -> io::Result<()> {
+        writeln!(
+            self.file,
+            "[{:.6}, \"m\", {}]",
+            time.as_secs_f64(),
+            json_string(label)
+        )
+    }
+}
+
+/// Keeps the output events in memory, to export them once the animessage has ended. Clones share the same events.
+#[derive(Clone, Default)]
+pub struct OutputLog {
+    events: Rc<RefCell<Vec<(Duration, String)>>>,
+}
+
+impl OutputLog {
+    pub(crate) fn events(&self) -> Vec<(Duration, String)> {
+        self.events.borrow().clone()
+    }
+}
+
+impl Renderer for OutputLog {
+    fn render(&mut self, time: Duration, output: &str) -> io::Result<()> {
+        self.events.borrow_mut().push((time, output.to_string()));
+        Ok(())
+    }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_json_lines() {
+        let path = std::env::temp_dir().join("animessage_recorder_test.cast");
+        let mut recorder = CastRecorder::create(&path, 80, 24).unwrap();
+        recorder
+            .render(Duration::from_millis(1500), "\u{1b}[1;1H\"hi\"\n")
+            .unwrap();
+        drop(recorder); // Flushes the file.
+
+        let cast = fs::read_to_string(&path).unwrap();
+        let mut lines = cast.lines();
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with(r#"{"version": 2, "width": 80, "height": 24"#));
+        assert_eq!(
+            lines.next(),
+            Some(r#"[1.500000, "o", "\u001b[1;1H\"hi\"\n"]"#)
+        );
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn headless_recording_simulates_delays() {
+        let path = std::env::temp_dir().join("animessage_headless_test.cast");
+        let recorder = CastRecorder::create(&path, 80, 24).unwrap();
+        let mut stage = Stage::new().with_headless(true).with_renderer(recorder);
+        let mut audio = Audio::new(false);
+        let started = std::time::Instant::now();
+        let animessage = "Hi\n--[PRINT]-- \"0.5\"\n--[WAIT]-- \"300\"\nBye\n--[PRINT]-- \"0\"";
+        display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio).unwrap();
+        drop(stage);
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let cast = fs::read_to_string(&path).unwrap();
+        let mut lines = cast.lines().rev();
+        assert_eq!(lines.next(), Some(r#"[301.000000, "m", "PRINT line 5"]"#));
+        assert_eq!(lines.next(), Some(r#"[301.000000, "o", "Bye\n"]"#));
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,139 @@
+use std::{cell::Ref, io::BufWriter, rc::Rc};
+
+use super::*;
+
+/// Receives what the stage draws at each flush : a terminal, a file, or a screen in memory.
+pub trait Renderer {
+    /// Writes the output of a flush, `time` being the time of the stage.
+    fn render(&mut self, time: Duration, output: &str) -> io::Result<()>;
+
+    /// Notes that a step of the animessage, such as a print or a marker, has just ended.
+    fn mark(&mut self, _time: Duration, _label: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The real terminal this renderer writes to, if it does.
+    fn terminal(&self) -> Option<&Term> {
+        None
+    }
+}
+
+/// Writes to the terminal Animessage runs in.
+pub struct TerminalRenderer {
+    term: Term,
+}
+
+impl TerminalRenderer {
+    pub fn new(term: Term) -> Self {
+        TerminalRenderer { term }
+    }
+}
+
+impl Renderer for TerminalRenderer {
+    fn render(&mut self, _time: Duration, output: &str) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        if terminal::is_raw_mode_enabled()? {
+            // Line feeds don't go back to the first column in raw mode, which the playback controls use.
+            stdout.write_all(output.replace('\n', "\r\n").as_bytes())?;
+        } else {
+            stdout.write_all(output.as_bytes())?;
+        }
+        stdout.flush()
+    }
+
+    fn terminal(&self) -> Option<&Term> {
+        Some(&self.term)
+    }
+}
+
+/// Interprets the output into a screen in memory, which can be read at any time. Clones share the same screen.
+#[derive(Clone)]
+pub struct MemoryRenderer {
+    screen: Rc<RefCell<VirtualTerminal>>,
+}
+
+impl MemoryRenderer {
+    pub fn new(columns: u16, rows: u16) -> Self {
+        MemoryRenderer {
+            screen: Rc::new(RefCell::new(VirtualTerminal::new(columns, rows))),
+        }
+    }
+
+    pub(crate) fn screen(&self) -> Ref<'_, VirtualTerminal> {
+        self.screen.borrow()
+    }
+}
+
+impl Renderer for MemoryRenderer {
+    fn render(&mut self, _time: Duration, output: &str) -> io::Result<()> {
+        self.screen.borrow_mut().feed(output);
+        Ok(())
+    }
+}
+
+/// Writes the text on screen at the end of each step to a file, without colors or escape codes. Steps that don't change the text are left out.
+pub struct TextRenderer {
+    screen: MemoryRenderer,
+    file: BufWriter<File>,
+    last_text: String,
+}
+
+impl TextRenderer {
+    pub fn create(path: &Path, columns: u16, rows: u16) -> io::Result<Self> {
+        Ok(TextRenderer {
+            screen: MemoryRenderer::new(columns, rows),
+            file: BufWriter::new(File::create(path)?),
+            last_text: String::new(),
+        })
+    }
+}
+
+impl Renderer for TextRenderer {
+    fn render(&mut self, time: Duration, output: &str) -> io::Result<()> {
+        self.screen.render(time, output)
+    }
+
+    fn mark(&mut self, time: Duration, label: &str) -> io::Result<()> {
+        let text = self
+            .screen
+            .screen()
+            .text()
+            .trim_end_matches('\n')
+            .to_string();
+        if text == self.last_text {
+            return Ok(());
+        }
+        writeln!(
+            self.file,
+            "--- {} at {:.3}s ---\n{}\n",
+            label,
+            time.as_secs_f64(),
+            text
+        )?;
+        self.last_text = text;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcripts_have_the_text_of_each_step() {
+        let path = std::env::temp_dir().join("animessage_transcript_test.txt");
+        let renderer = TextRenderer::create(&path, 80, 24).unwrap();
+        let mut stage = Stage::new().with_headless(true).with_renderer(renderer);
+        let mut audio = Audio::new(false);
+        let animessage = "Hello\n--[PRINT_LINE]-- \"0\"\n--[MARKER]-- \"same text\"\n--[CLEAR]--\nBye\n--[PRINT]-- \"0.1\"";
+        display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio).unwrap();
+        drop(stage);
+
+        let transcript = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            transcript,
+            "--- PRINT line 2 at 0.000s ---\nHello\n\n--- PRINT line 6 at 0.300s ---\nBye\n\n"
+        );
+        let _ = fs::remove_file(path);
+    }
+}
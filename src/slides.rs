@@ -0,0 +1,324 @@
+use super::*;
+use std::rc::Rc;
+
+const SHOWING_FUNCTIONS: [&str; 4] = [PRINT, PRINT_LINE, IMAGE, VIDEO]; // A slide is made of the lines before a boundary if they show something.
+
+/// Slides of an animessage played with --present. One starts at each --[CLEAR]-- or --[SLIDE]-- which follows something shown.
+pub(crate) struct Slides {
+    slides: Vec<Slide>,
+    lines: Rc<Vec<String>>, // Lines they were found in. Any change to them copies them while they're shared here.
+}
+
+struct Slide {
+    start: usize, // Index of the line of its --[CLEAR]-- or --[SLIDE]--, or 0 for the first slide.
+    marker: Option<String>, // Name of the last marker reached in the slide.
+}
+
+/// Where the viewer asked to go after a slide.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Navigation {
+    Next,
+    GoTo(usize), // Slide index.
+    Quit,
+}
+
+pub(crate) fn is_slide_boundary(line_trimmed: &str) -> bool {
+    line_trimmed == CLEAR || line_trimmed == SLIDE
+}
+
+impl Slides {
+    pub(crate) fn new(lines: &Rc<Vec<String>>) -> Self {
+        let mut slides = vec![Slide {
+            start: 0,
+            marker: None,
+        }];
+        let mut shows = false;
+        let mut next_marker = None; // Marker after what's shown, which names the next slide if one starts before anything else is shown.
+        for (index, line) in lines.iter().enumerate() {
+            let line = line.trim();
+            if is_slide_boundary(line) && shows {
+                let marker = next_marker
+                    .take()
+                    .or_else(|| slides.last().and_then(|slide| slide.marker.clone()));
+                slides.push(Slide {
+                    start: index,
+                    marker,
+                });
+                shows = false;
+            } else if let Some(marker) = marker_name(line) {
+                next_marker = Some(marker.to_string());
+            } else if SHOWING_FUNCTIONS
+                .iter()
+                .any(|function| line.starts_with(function))
+            {
+                shows = true;
+                if let (Some(slide), Some(marker)) = (slides.last_mut(), next_marker.take()) {
+                    slide.marker = Some(marker);
+                }
+            }
+        }
+        if let (Some(slide), Some(marker)) = (slides.last_mut(), next_marker) {
+            slide.marker = Some(marker);
+        }
+        Slides {
+            slides,
+            lines: lines.clone(),
+        }
+    }
+
+    /// Slides of `lines`, from `cache` unless they were found in other lines.
+    pub(crate) fn cached<'a>(cache: &'a mut Option<Slides>, lines: &Rc<Vec<String>>) -> &'a Slides {
+        if cache
+            .as_ref()
+            .is_some_and(|slides| !Rc::ptr_eq(&slides.lines, lines))
+        {
+            *cache = None;
+        }
+        cache.get_or_insert_with(|| Slides::new(lines))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.slides.len()
+    }
+
+    /// Index of the slide the line at `line_index` is part of.
+    pub(crate) fn index_at(&self, line_index: usize) -> usize {
+        self.slides
+            .iter()
+            .rposition(|slide| slide.start <= line_index)
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn start(&self, index: usize) -> usize {
+        self.slides[index].start
+    }
+
+    // "intro | 3/17"
+    fn footer(&self, index: usize) -> String {
+        let counter = format!("{}/{}", index + 1, self.len());
+        match &self.slides[index].marker {
+            Some(marker) => format!("{} | {}", marker, counter),
+            None => counter,
+        }
+    }
+}
+
+/// Reads the keys going from slide to slide : Right, Down, Page Down, Space or Enter for the next one, Left, Up, Page Up or Backspace
+/// for the previous one, Home and End for the first and last ones, G then a number and Enter for any other, and Q or Escape to quit.
+pub(crate) struct SlideKeys {
+    held: Vec<Keycode>, // Keys of the last check, which are only handled once even if they're held down.
+    typed_number: Option<String>,
+}
+
+impl SlideKeys {
+    /// Keys ignored until they're released, as they were pressed for something else.
+    pub(crate) fn new(held: Vec<Keycode>) -> Self {
+        SlideKeys {
+            held,
+            typed_number: None,
+        }
+    }
+
+    /// Where to go after the slide `current` of `count` slides, once `keys` are pressed.
+    pub(crate) fn navigation(
+        &mut self,
+        keys: &[Keycode],
+        current: usize,
+        count: usize,
+    ) -> Option<Navigation> {
+        let pressed: Vec<Keycode> = keys
+            .iter()
+            .filter(|key| !self.held.contains(key))
+            .cloned()
+            .collect();
+        self.held = keys.to_vec();
+        for key in pressed {
+            if let Some(number) = &mut self.typed_number {
+                match key {
+                    Keycode::Enter => {
+                        let slide_number = number.parse::<usize>().ok();
+                        self.typed_number = None;
+                        if let Some(slide_number) =
+                            slide_number.filter(|number| (1..=count).contains(number))
+                        {
+                            return Some(Navigation::GoTo(slide_number - 1));
+                        }
+                    }
+                    Keycode::Escape => self.typed_number = None,
+                    Keycode::Backspace => {
+                        number.pop();
+                    }
+                    key => number.extend(digit(key)),
+                }
+                continue;
+            }
+            let navigation = match key {
+                Keycode::Right
+                | Keycode::Down
+                | Keycode::PageDown
+                | Keycode::Space
+                | Keycode::Enter => Navigation::Next,
+                Keycode::Left | Keycode::Up | Keycode::PageUp | Keycode::Backspace => {
+                    Navigation::GoTo(current.saturating_sub(1))
+                }
+                Keycode::Home => Navigation::GoTo(0),
+                Keycode::End => Navigation::GoTo(count - 1),
+                Keycode::G => {
+                    self.typed_number = Some(String::new());
+                    continue;
+                }
+                Keycode::Q | Keycode::Escape => Navigation::Quit,
+                _ => continue,
+            };
+            return Some(navigation);
+        }
+        None
+    }
+
+    /// Slide number typed after G so far.
+    pub(crate) fn typed_number(&self) -> Option<&str> {
+        self.typed_number.as_deref()
+    }
+}
+
+fn digit(key: Keycode) -> Option<char> {
+    let digit = match key {
+        Keycode::Key0 | Keycode::Numpad0 => '0',
+        Keycode::Key1 | Keycode::Numpad1 => '1',
+        Keycode::Key2 | Keycode::Numpad2 => '2',
+        Keycode::Key3 | Keycode::Numpad3 => '3',
+        Keycode::Key4 | Keycode::Numpad4 => '4',
+        Keycode::Key5 | Keycode::Numpad5 => '5',
+        Keycode::Key6 | Keycode::Numpad6 => '6',
+        Keycode::Key7 | Keycode::Numpad7 => '7',
+        Keycode::Key8 | Keycode::Numpad8 => '8',
+        Keycode::Key9 | Keycode::Numpad9 => '9',
+        _ => return None,
+    };
+    Some(digit)
+}
+
+/// Shows the footer of the slide at `index`, unless it's disabled.
+pub(crate) fn show_slide_footer(stage: &mut Stage, slides: &Slides, index: usize) {
+    if stage.shows_slide_footer() {
+        stage.print_footer(&slides.footer(index));
+    }
+}
+
+/// Waits at the end of the slide `current` for a key telling where to go next. Doesn't wait in headless mode.
+pub(crate) fn wait_for_slide_key(
+    stage: &mut Stage,
+    slides: &Slides,
+    current: usize,
+) -> AnyResult<Navigation> {
+    show_slide_footer(stage, slides, current);
+    if stage.is_headless() {
+        return Ok(Navigation::Next);
+    }
+    stage.suspend_controls(); // Their keys go from slide to slide now.
+    let mut key_reader = match stage.key_reader() {
+        Ok(key_reader) => key_reader,
+        Err(err) => {
+            warn!("Can't read the keys of the terminal, so the next slide is shown right away. Error details : {}", err);
+            stage.resume_controls();
+            return Ok(Navigation::Next);
+        }
+    };
+    let mut slide_keys = SlideKeys::new(key_reader.pressed_keys()?);
+    let navigation = loop {
+        let keys = key_reader.pressed_keys()?;
+        if keys.contains(&Keycode::C) && keys.contains(&Keycode::LControl) {
+            break Navigation::Quit; // Ctrl + C doesn't stop Animessage by itself in raw mode.
+        }
+        if let Some(marker) = stage.bound_marker(&keys) {
+            stage.interrupt(Interruption::JumpTo(marker));
+            break Navigation::Next;
+        }
+        let typed_number = slide_keys.typed_number().map(str::to_string);
+        if let Some(navigation) = slide_keys.navigation(&keys, current, slides.len()) {
+            break navigation;
+        }
+        match slide_keys.typed_number() {
+            Some(number) if typed_number.as_deref() != Some(number) => {
+                stage.print_footer(&format!("Go to slide : {}", number))
+            }
+            None if typed_number.is_some() => {
+                stage.print_footer("");
+                show_slide_footer(stage, slides, current);
+            }
+            _ => (),
+        }
+        stage.poll_sleep(Duration::from_millis(10));
+    };
+    drop(key_reader);
+    stage.resume_controls();
+    Ok(navigation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slides_start_at_clears_after_something_shown() {
+        let animessage = "--[CLEAR]--\nTitle\n--[PRINT]-- \"0\"\n--[MARKER]-- \"intro\"\n--[SLIDE]--\nHello\n--[PRINT]-- \"0\"\n--[CLEAR]--\n--[CLEAR]--\nBye\n--[PRINT]-- \"0\"";
+        let lines = Rc::new(animessage.lines().map(str::to_string).collect());
+        let slides = Slides::new(&lines);
+        assert_eq!(slides.len(), 3);
+        assert_eq!(slides.start(1), 4);
+        assert_eq!(slides.index_at(8), 2);
+        assert_eq!(slides.footer(0), "1/3");
+        assert_eq!(slides.footer(2), "intro | 3/3");
+
+        let screen = MemoryRenderer::new(20, 5);
+        let mut stage = Stage::new()
+            .with_headless(true)
+            .with_size(20, 5)
+            .with_renderer(screen.clone())
+            .with_present(true)
+            .with_slide_footer(true);
+        let mut audio = Audio::new(false);
+        display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio).unwrap();
+        assert_eq!(
+            screen.screen().text().trim_end(),
+            "Bye\n\n\n\n         intro | 3/3"
+        );
+    }
+
+    #[test]
+    fn keys_go_from_slide_to_slide() {
+        let mut slide_keys = SlideKeys::new(vec![Keycode::Right]);
+        assert_eq!(slide_keys.navigation(&[Keycode::Right], 1, 5), None); // Still held down.
+        assert_eq!(slide_keys.navigation(&[], 1, 5), None);
+        assert_eq!(
+            slide_keys.navigation(&[Keycode::Right], 1, 5),
+            Some(Navigation::Next)
+        );
+        assert_eq!(
+            slide_keys.navigation(&[Keycode::Left], 1, 5),
+            Some(Navigation::GoTo(0))
+        );
+        assert_eq!(
+            slide_keys.navigation(&[Keycode::End], 1, 5),
+            Some(Navigation::GoTo(4))
+        );
+
+        assert_eq!(slide_keys.navigation(&[Keycode::G], 1, 5), None);
+        assert_eq!(
+            slide_keys.navigation(&[Keycode::Key1, Keycode::Key2], 1, 5),
+            None
+        );
+        assert_eq!(slide_keys.typed_number(), Some("12"));
+        assert_eq!(slide_keys.navigation(&[Keycode::Enter], 1, 5), None); // There are only 5 slides.
+        assert_eq!(slide_keys.navigation(&[Keycode::G], 1, 5), None);
+        assert_eq!(slide_keys.navigation(&[Keycode::Key3], 1, 5), None);
+        assert_eq!(
+            slide_keys.navigation(&[Keycode::Enter], 1, 5),
+            Some(Navigation::GoTo(2))
+        );
+        assert_eq!(
+            slide_keys.navigation(&[Keycode::Q], 1, 5),
+            Some(Navigation::Quit)
+        );
+    }
+}
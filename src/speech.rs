@@ -0,0 +1,129 @@
+use std::{
+    env,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+};
+
+use super::*;
+
+const SPEECH_PROGRAMS: [&str; 3] = ["espeak-ng", "espeak", "say"]; // In order of preference. "say" comes with macOS.
+
+/// Reads text aloud.
+pub(crate) trait SpeechEngine {
+    /// Starts reading `text` in the background, after what is being read.
+    fn speak(&mut self, text: &str) -> AnyResult<()>;
+}
+
+/// Speech through a text-to-speech program installed on the system, which reads the text from its input.
+/// Texts are read one after the other by a thread, so that voices don't overlap and the animessage goes on meanwhile.
+pub(crate) struct ProgramSpeech {
+    texts: Option<Sender<String>>, // Taken when dropped, so that the thread ends once it has read them all.
+    reader: Option<JoinHandle<()>>,
+}
+
+impl ProgramSpeech {
+    /// Finds the first program of `SPEECH_PROGRAMS` in the PATH.
+    pub(crate) fn find() -> Option<Self> {
+        let paths: Vec<PathBuf> = env::var_os("PATH")
+            .map(|paths| env::split_paths(&paths).collect())
+            .unwrap_or_default();
+        let program = SPEECH_PROGRAMS.iter().find_map(|program| {
+            paths
+                .iter()
+                .map(|dir| dir.join(program))
+                .find(|path| path.is_file())
+        })?;
+        let (texts, receiver) = mpsc::channel::<String>();
+        let reader = thread::spawn(move || {
+            for text in receiver {
+                if let Err(err) = read_aloud(&program, &text) {
+                    warn!("Can't read {:?} aloud. Error details : {}", text, err);
+                }
+            }
+        });
+        Some(ProgramSpeech {
+            texts: Some(texts),
+            reader: Some(reader),
+        })
+    }
+}
+
+impl SpeechEngine for ProgramSpeech {
+    fn speak(&mut self, text: &str) -> AnyResult<()> {
+        if let Some(texts) = &self.texts {
+            texts.send(text.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ProgramSpeech {
+    fn drop(&mut self) {
+        drop(self.texts.take());
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join(); // What's left to read isn't cut off when Animessage exits.
+        }
+    }
+}
+
+/// Runs `program` until it has read `text`, which is written to its input so that it can't be taken for an option.
+fn read_aloud(program: &Path, text: &str) -> AnyResult<()> {
+    let stdin_args: &[&str] = if program.ends_with("say") {
+        &["-f", "-"]
+    } else {
+        &["--stdin"]
+    };
+    let mut child = Command::new(program)
+        .args(stdin_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    } // Closed here, so that the program knows the text has ended.
+    child.wait()?;
+    Ok(())
+}
+
+/// Logs the text instead of reading it, when audio is disabled or no speech program has been found.
+pub(crate) struct LogSpeech;
+
+impl SpeechEngine for LogSpeech {
+    fn speak(&mut self, text: &str) -> AnyResult<()> {
+        info!("TTS : {:?}", text);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    struct RecordingSpeech(Rc<RefCell<Vec<String>>>);
+
+    impl SpeechEngine for RecordingSpeech {
+        fn speak(&mut self, text: &str) -> AnyResult<()> {
+            self.0.borrow_mut().push(text.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tts_and_printed_steps_are_spoken() {
+        let spoken = Rc::new(RefCell::new(Vec::new()));
+        let mut audio = Audio::new(false)
+            .with_speak_steps(true)
+            .with_speech(Box::new(RecordingSpeech(Rc::clone(&spoken))));
+        let mut stage = Stage::new().with_headless(true);
+        let animessage = "--[TTS]-- \"Welcome\"\nHello\n--[PRINT_LINE]-- \"0\"";
+        display_animessage(animessage, true, false, false, 0, &mut stage, &mut audio).unwrap();
+
+        assert_eq!(*spoken.borrow(), vec!["Welcome", "Hello"]);
+    }
+}
@@ -0,0 +1,195 @@
+use console_engine::{pixel, screen::Screen};
+
+use super::*;
+
+pub(crate) type Position = (i32, i32); // (column, row)
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl FromStr for Easing {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Easing::Linear),
+            "ease-in" => Ok(Easing::EaseIn),
+            "ease-out" => Ok(Easing::EaseOut),
+            "ease-in-out" => Ok(Easing::EaseInOut),
+            _ => anyhow::bail!(
+                "No easing with this name. Use \"linear\", \"ease-in\", \"ease-out\" or \"ease-in-out\"."
+            ),
+        }
+    }
+}
+
+impl Easing {
+    /// Maps the elapsed fraction of a movement (0.0 to 1.0) to the travelled fraction of its distance.
+    pub(crate) fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+struct Tween {
+    from: Position,
+    to: Position,
+    start: Duration, // Time of the stage.
+    duration: Duration,
+    easing: Easing,
+}
+
+impl Tween {
+    fn position(&self, now: Duration) -> Position {
+        let t = if self.duration.is_zero() {
+            1.0
+        } else {
+            now.saturating_sub(self.start).as_secs_f64() / self.duration.as_secs_f64()
+        };
+        let progress = self.easing.apply(t);
+        let lerp = |a: i32, b: i32| a + ((b - a) as f64 * progress).round() as i32;
+        (lerp(self.from.0, self.to.0), lerp(self.from.1, self.to.1))
+    }
+
+    fn finished(&self, now: Duration) -> bool {
+        now.saturating_sub(self.start) >= self.duration
+    }
+}
+
+pub(crate) struct Sprite {
+    pub(crate) lines: Vec<String>,
+    pub(crate) screen: Screen, // Same art, ready to be drawn by the compositor.
+}
+
+impl Sprite {
+    fn new(art: &str) -> Self {
+        let lines: Vec<String> = art.lines().map(|l| l.to_string()).collect();
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let mut screen = Screen::new_fill(width as u32, lines.len() as u32, pixel::pxl(' '));
+        for (row, line) in lines.iter().enumerate() {
+            screen.print(0, row as i32, line);
+        }
+        Sprite { lines, screen }
+    }
+}
+
+struct Placement {
+    tween: Tween,
+    drawn_at: Option<Position>,
+}
+
+/// Named ASCII sprites and the movements they're currently doing on screen.
+#[derive(Default)]
+pub(crate) struct Sprites {
+    defs: HashMap<String, Sprite>,
+    placed: BTreeMap<String, Placement>,
+}
+
+impl Sprites {
+    pub(crate) fn define(&mut self, name: &str, art: &str) {
+        self.defs.insert(name.to_string(), Sprite::new(art));
+    }
+
+    pub(crate) fn start_move(
+        &mut self,
+        name: &str,
+        from: Position,
+        to: Position,
+        duration: Duration,
+        easing: Easing,
+        now: Duration,
+    ) -> AnyResult<()> {
+        if !self.defs.contains_key(name) {
+            anyhow::bail!(
+                "No sprite named {:?}. Define it with --[SPRITE]-- first.",
+                name
+            );
+        }
+        let tween = Tween {
+            from,
+            to,
+            start: now,
+            duration,
+            easing,
+        };
+        let drawn_at = self.placed.get(name).and_then(|p| p.drawn_at);
+        self.placed
+            .insert(name.to_string(), Placement { tween, drawn_at });
+        Ok(())
+    }
+
+    pub(crate) fn is_moving(&self, now: Duration) -> bool {
+        self.placed.values().any(|p| !p.tween.finished(now))
+    }
+
+    /// Takes every sprite off the screen. Definitions are kept.
+    pub(crate) fn clear(&mut self) {
+        self.placed.clear();
+    }
+
+    /// Sprites on screen with the position they've been drawn at by the last call to `update`, in a stable order.
+    pub(crate) fn frame(&self) -> Vec<(Position, &Sprite)> {
+        self.placed
+            .iter()
+            .filter_map(|(name, p)| p.drawn_at.map(|position| (position, &self.defs[name])))
+            .collect()
+    }
+
+    /// Moves the sprites to their position at `now`. Returns `None` if none of them moved, else the areas (position, columns, rows) they've left.
+    pub(crate) fn update(&mut self, now: Duration) -> Option<Vec<(Position, usize, usize)>> {
+        let mut moved = false;
+        let mut left_areas = Vec::new();
+        for (name, p) in self.placed.iter_mut() {
+            let position = p.tween.position(now);
+            if p.drawn_at != Some(position) {
+                moved = true;
+                if let Some(previous) = p.drawn_at {
+                    let screen = &self.defs[name].screen;
+                    left_areas.push((
+                        previous,
+                        screen.get_width() as usize,
+                        screen.get_height() as usize,
+                    ));
+                }
+                p.drawn_at = Some(position);
+            }
+        }
+        moved.then_some(left_areas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easings_start_and_end_in_place() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+        }
+        assert!(Easing::EaseIn.apply(0.25) < 0.25);
+        assert!(Easing::EaseOut.apply(0.25) > 0.25);
+    }
+}
@@ -0,0 +1,637 @@
+use crossterm::{queue, style};
+
+use super::*;
+
+const SPRITES_FRAME_DURATION: Duration = Duration::from_millis(33);
+const DEFAULT_SIZE: (u16, u16) = (80, 24); // (columns, rows) when the terminal size is unknown, and in headless mode.
+const CONTROLS_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Why the animessage should stop what it's doing, as asked by the viewer with the playback controls or a key bound to a marker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Interruption {
+    FinishStep,
+    PreviousStep,
+    NextStep, // Finishes the current step unless there's a step gone back from to go forward to.
+    NextMarker,
+    JumpTo(String), // Marker name.
+    Quit,
+}
+
+/// Everything the animessage draws goes through the stage, which either prints it right away or composes it in memory first.
+/// What has been drawn is sent to every renderer at each flush, such as the terminal or a recording.
+pub struct Stage {
+    compositor: Option<Compositor>,
+    sprites: Sprites,
+    pending: Vec<u8>, // Output since the last flush.
+    renderers: Vec<Box<dyn Renderer>>,
+    size: Option<(u16, u16)>, // Replaces the terminal's size.
+    clock: Box<dyn Clock>,
+    headless: bool,
+    controls: Option<Controls>,
+    interruption: Option<Interruption>, // Delays aren't waited for until it's handled.
+    paused_time: Duration, // Clock time spent paused, which isn't part of the stage's time.
+    screen: Option<VirtualTerminal>, // What's on screen, kept to go back to previous steps.
+    stats: Stats,
+    global_keys: bool,
+    playback_keys: bool,
+    bindings: BTreeMap<String, (ExpectedKeys, String)>, // K: keys as written in the animessage / V: these keys and the marker they go to.
+    present: bool,
+    slide_footer: bool,
+}
+
+impl Stage {
+    /// A stage without renderers, which draws nowhere until some are added.
+    pub fn new() -> Self {
+        Stage {
+            compositor: None,
+            sprites: Sprites::default(),
+            pending: Vec::new(),
+            renderers: Vec::new(),
+            size: None,
+            clock: Box::new(RealClock::new()),
+            headless: false,
+            controls: None,
+            interruption: None,
+            paused_time: Duration::ZERO,
+            screen: None,
+            stats: Stats::default(),
+            global_keys: false,
+            playback_keys: true,
+            bindings: BTreeMap::new(),
+            present: false,
+            slide_footer: false,
+        }
+    }
+
+    /// Draws into a double-buffered cell grid of the terminal's size instead of printing directly.
+    pub fn with_compositor(mut self, enabled: bool) -> Self {
+        self.compositor = if enabled {
+            let (columns, rows) = self.size();
+            Some(Compositor::new(columns as u32, rows as u32))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Doesn't wait : delays only move the virtual clock of the stage forward. Keys and prompts aren't waited for either.
+    pub fn with_headless(mut self, enabled: bool) -> Self {
+        self.headless = enabled;
+        if enabled {
+            self.clock = Box::new(VirtualClock::default());
+        }
+        let (columns, rows) = self.size();
+        if let Some(compositor) = &mut self.compositor {
+            compositor.resize(columns as u32, rows as u32);
+        }
+        self
+    }
+
+    /// Waits on this clock instead of the real time.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Draws for a terminal of this size instead of the real one's.
+    pub fn with_size(mut self, columns: u16, rows: u16) -> Self {
+        self.size = Some((columns, rows));
+        if let Some(compositor) = &mut self.compositor {
+            compositor.resize(columns as u32, rows as u32);
+        }
+        self
+    }
+
+    /// Sends what is drawn to `renderer` too. The size of the stage is the one of the first terminal renderer.
+    pub fn with_renderer(mut self, renderer: impl Renderer + 'static) -> Self {
+        self.renderers.push(Box::new(renderer));
+        self
+    }
+
+    /// Lets the viewer pause, go back to the previous step, finish the current one, skip to the next marker or quit while delays are waited for.
+    /// What's on screen is kept in memory to be shown again when going back.
+    pub fn with_controls(mut self, controls: Controls) -> Self {
+        let (columns, rows) = self.size();
+        self.controls = Some(controls);
+        self.screen = Some(VirtualTerminal::new(columns, rows));
+        self
+    }
+
+    /// Reads the keys waited for from the whole keyboard instead of the terminal's input.
+    pub fn with_global_keys(mut self, enabled: bool) -> Self {
+        self.global_keys = enabled;
+        self
+    }
+
+    /// Whether the playback controls handle Space, Enter, the arrows, N and Q. Keys bound to markers work either way.
+    pub fn with_playback_keys(mut self, enabled: bool) -> Self {
+        self.playback_keys = enabled;
+        self
+    }
+
+    /// Plays the animessage as slides, waiting for a key at the end of each one.
+    pub fn with_present(mut self, enabled: bool) -> Self {
+        self.present = enabled;
+        self
+    }
+
+    /// Shows the slide number and the current marker on the last row while presenting.
+    pub fn with_slide_footer(mut self, enabled: bool) -> Self {
+        self.slide_footer = enabled;
+        self
+    }
+
+    /// Stops the playback controls, giving the terminal back as it was.
+    pub fn release_controls(&mut self) {
+        self.controls = None;
+    }
+
+    /// The real terminal the stage draws to, if it does.
+    pub(crate) fn term(&self) -> Option<&Term> {
+        self.renderers
+            .iter()
+            .find_map(|renderer| renderer.terminal())
+    }
+
+    pub(crate) fn is_composited(&self) -> bool {
+        self.compositor.is_some()
+    }
+
+    pub(crate) fn is_headless(&self) -> bool {
+        self.headless
+    }
+
+    /// Whether the output goes somewhere else than a terminal.
+    pub(crate) fn is_recording(&self) -> bool {
+        self.renderers
+            .iter()
+            .any(|renderer| renderer.terminal().is_none())
+    }
+
+    /// Whether the output only goes to an interactive terminal, which can then be used directly.
+    pub(crate) fn is_tty(&self) -> bool {
+        self.renderers.len() == 1 && self.term().is_some_and(|term| term.is_term())
+    }
+
+    /// (columns, rows)
+    pub fn size(&self) -> (u16, u16) {
+        if let Some(size) = self.size {
+            return size;
+        }
+        self.term()
+            .and_then(|term| term.size_checked())
+            .map_or(DEFAULT_SIZE, |(rows, columns)| (columns, rows))
+    }
+
+    /// Whether the animessage is played as slides, waiting for a key at the end of each one.
+    pub(crate) fn presents(&self) -> bool {
+        self.present
+    }
+
+    /// Whether the slide number and the current marker are shown on the last row, which is only done while presenting.
+    pub(crate) fn shows_slide_footer(&self) -> bool {
+        self.present && self.slide_footer
+    }
+
+    /// Whether delays are waited for, instead of only moving a virtual clock forward.
+    pub(crate) fn waits(&self) -> bool {
+        self.clock.waits()
+    }
+
+    /// Time of the stage's clock, real or simulated.
+    pub(crate) fn now(&self) -> Duration {
+        self.clock.now().saturating_sub(self.paused_time)
+    }
+
+    /// What the animessage does, counted while it's gone through with no_exec.
+    pub(crate) fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub(crate) fn stats_mut(&mut self) -> &mut Stats {
+        &mut self.stats
+    }
+
+    /// What the viewer asked for and hasn't been handled yet.
+    pub(crate) fn interruption(&self) -> Option<Interruption> {
+        self.interruption.clone()
+    }
+
+    /// What's on screen, if it's kept to go back to previous steps.
+    pub(crate) fn screen(&self) -> Option<&VirtualTerminal> {
+        self.screen.as_ref()
+    }
+
+    /// Draws a screen kept by `screen` again, in place of what's shown. Sprites are forgotten, but their drawings are part of the screen.
+    pub(crate) fn restore_screen(&mut self, screen: &ScreenCheckpoint) {
+        let _ = self.clear();
+        let mut rows: Vec<Vec<Cell>> = screen
+            .rows()
+            .map(|row| {
+                let length = row
+                    .iter()
+                    .rposition(|cell| *cell != Cell::blank())
+                    .map_or(0, |index| index + 1);
+                row[..length].to_vec()
+            })
+            .collect();
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+        self.print_cells(&rows, Some((0, 0)));
+        if !screen.title().is_empty() {
+            self.set_title(screen.title());
+        }
+        match screen.cursor() {
+            Some((column, row)) => {
+                let _ = self.move_cursor(column, row);
+                self.show_cursor(true);
+            }
+            None => self.show_cursor(false),
+        }
+    }
+
+    pub(crate) fn is_interrupted(&self) -> bool {
+        self.interruption.is_some()
+    }
+
+    /// Whether the viewer wants to leave the current step without showing the rest of it.
+    pub(crate) fn is_stopping(&self) -> bool {
+        matches!(
+            self.interruption,
+            Some(
+                Interruption::PreviousStep
+                    | Interruption::NextMarker
+                    | Interruption::JumpTo(_)
+                    | Interruption::Quit
+            )
+        )
+    }
+
+    /// Interrupts the animessage, unless it's already quitting.
+    pub(crate) fn interrupt(&mut self, interruption: Interruption) {
+        if self.interruption != Some(Interruption::Quit) {
+            self.interruption = Some(interruption);
+        }
+    }
+
+    pub(crate) fn clear_interruption(&mut self) {
+        self.interruption = None;
+    }
+
+    /// Handles the keys pressed since the last check. While paused, this only returns once resumed or interrupted.
+    pub(crate) fn check_controls(&mut self) {
+        let Some(controls) = &self.controls else {
+            return;
+        };
+        let paused_at = self.clock.now();
+        let mut paused = false;
+        loop {
+            let Some(key) = controls.try_recv() else {
+                if !paused {
+                    break;
+                }
+                sleep(CONTROLS_POLL_INTERVAL);
+                continue;
+            };
+            let control = Control::from_key(key).filter(|_| self.playback_keys);
+            let interruption = match (self.bound_marker(&keycodes(key)), control) {
+                (Some(marker), _) => Interruption::JumpTo(marker),
+                (None, Some(Control::Pause)) => {
+                    paused = !paused;
+                    continue;
+                }
+                (None, Some(Control::FinishStep)) => Interruption::FinishStep,
+                (None, Some(Control::PreviousStep)) => Interruption::PreviousStep,
+                (None, Some(Control::NextStep)) => Interruption::NextStep,
+                (None, Some(Control::NextMarker)) => Interruption::NextMarker,
+                (None, Some(Control::Quit)) => Interruption::Quit,
+                (None, None) => continue,
+            };
+            if self.interruption != Some(Interruption::Quit) {
+                self.interruption = Some(interruption);
+            }
+            paused = false;
+        }
+        self.paused_time += self.clock.now().saturating_sub(paused_at);
+    }
+
+    /// Goes to `marker` whenever `keys` are pressed, until unbound. `name` is how the keys are written in the animessage.
+    pub(crate) fn bind(&mut self, name: &str, keys: ExpectedKeys, marker: &str) {
+        if self.controls.is_none() && !self.headless {
+            warn!(
+                "Keys {:?} can't go to marker {:?} : keys are only read with the playback controls, which are off in debug mode and outside of a terminal.",
+                name, marker
+            );
+        }
+        self.bindings
+            .insert(name.to_string(), (keys, marker.to_string()));
+    }
+
+    /// Removes the binding of the keys written `name`, or every binding.
+    pub(crate) fn unbind(&mut self, name: Option<&str>) {
+        match name {
+            Some(name) => {
+                if self.bindings.remove(name).is_none() {
+                    warn!(
+                        "Keys {:?} aren't bound to a marker, so they can't be unbound.",
+                        name
+                    );
+                }
+            }
+            None => self.bindings.clear(),
+        }
+    }
+
+    /// Marker the pressed `keys` are bound to, if any.
+    pub(crate) fn bound_marker(&self, keys: &[Keycode]) -> Option<String> {
+        self.bindings
+            .values()
+            .find(|(bound_keys, _marker)| bound_keys.pressed(keys).is_some())
+            .map(|(_bound_keys, marker)| marker.clone())
+    }
+
+    /// Reads the keys expected by the animessage, from the terminal or the whole keyboard.
+    pub(crate) fn key_reader(&self) -> AnyResult<Box<dyn KeyReader>> {
+        Ok(if self.global_keys {
+            Box::new(GlobalKeys::new())
+        } else {
+            Box::new(TerminalKeys::new()?)
+        })
+    }
+
+    /// Lets something else read the keys, such as a prompt.
+    pub(crate) fn suspend_controls(&mut self) {
+        if let Some(controls) = &self.controls {
+            controls.suspend();
+        }
+    }
+
+    pub(crate) fn resume_controls(&mut self) {
+        if let Some(controls) = &self.controls {
+            controls.resume();
+        }
+    }
+
+    pub(crate) fn print(&mut self, text: &str) {
+        match &mut self.compositor {
+            Some(compositor) => compositor.print(text),
+            None => self.pending.extend_from_slice(text.as_bytes()),
+        }
+    }
+
+    /// Prints rows of colored cells, one below the other, starting from `position` or else from the cursor.
+    pub(crate) fn print_cells(&mut self, rows: &[Vec<Cell>], position: Option<Position>) {
+        for (index, row) in rows.iter().enumerate() {
+            if let Some((column, first_row)) = position {
+                let (column, row_number) =
+                    (column.max(0) as usize, first_row.max(0) as usize + index);
+                self.queue_move_cursor(column, row_number);
+            }
+            match &mut self.compositor {
+                Some(compositor) => compositor.print_cells(row),
+                None => self
+                    .pending
+                    .extend_from_slice(cells_to_ansi(row).as_bytes()),
+            }
+            if position.is_none() {
+                self.print("\n");
+            }
+        }
+        self.flush();
+    }
+
+    /// Ends a tick : what has been printed since the last one and the sprites' new positions are now shown on screen.
+    pub(crate) fn flush(&mut self) {
+        let left_areas = self.sprites.update(self.now());
+        match &mut self.compositor {
+            Some(compositor) => {
+                let overlays: Vec<_> = self
+                    .sprites
+                    .frame()
+                    .into_iter()
+                    .map(|(position, sprite)| (position, &sprite.screen))
+                    .collect();
+                if let Err(err) = compositor.flush(&mut self.pending, &overlays) {
+                    warn!(
+                        "PRINT ERROR : Can't flush the compositor. Error details below : \n{:#?}",
+                        err
+                    )
+                }
+            }
+            None => {
+                if let Some(left_areas) = left_areas {
+                    self.draw_sprites(&left_areas);
+                }
+            }
+        }
+        self.write_pending();
+    }
+
+    fn write_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let now = self.now();
+        let output = String::from_utf8_lossy(&self.pending);
+        if let Some(screen) = &mut self.screen {
+            screen.feed(&output);
+        }
+        for renderer in &mut self.renderers {
+            if let Err(err) = renderer.render(now, &output) {
+                warn!(
+                    "PRINT ERROR : Can't write the output. Error details below : \n{:#?}",
+                    err
+                )
+            }
+        }
+        self.pending.clear();
+    }
+
+    /// Tells the renderers that a step of the animessage has ended, with what has been drawn so far.
+    pub(crate) fn mark(&mut self, label: &str) {
+        self.flush();
+        let now = self.now();
+        for renderer in &mut self.renderers {
+            if let Err(err) = renderer.mark(now, label) {
+                warn!(
+                    "PRINT ERROR : Can't write the output. Error details below : \n{:#?}",
+                    err
+                )
+            }
+        }
+    }
+
+    /// Waits between two checks of something outside the animessage, such as a key : `duration` is real time even if the clock doesn't wait.
+    pub(crate) fn poll_sleep(&mut self, duration: Duration) {
+        if self.waits() {
+            self.sleep(duration);
+        } else {
+            self.flush();
+            sleep(duration);
+        }
+    }
+
+    /// Sleeps for `duration`, animating the moving sprites and handling the playback controls in the meantime.
+    /// Returns right away if the viewer has interrupted the animessage.
+    pub(crate) fn sleep(&mut self, duration: Duration) {
+        let end = self.now() + duration;
+        loop {
+            self.check_controls();
+            let now = self.now();
+            if self.is_interrupted() || now >= end {
+                return;
+            }
+            let moving = self.sprites.is_moving(now);
+            let slice = if moving {
+                SPRITES_FRAME_DURATION
+            } else if self.controls.is_some() {
+                CONTROLS_POLL_INTERVAL
+            } else {
+                end - now
+            };
+            self.clock.sleep(slice.min(end - now));
+            if moving {
+                self.flush(); // Also draws the sprites at the end of their movement, once it's passed.
+            }
+        }
+    }
+
+    pub(crate) fn define_sprite(&mut self, name: &str, art: &str) {
+        self.sprites.define(name, art);
+    }
+
+    pub(crate) fn move_sprite(
+        &mut self,
+        name: &str,
+        from: Position,
+        to: Position,
+        duration: Duration,
+        easing: Easing,
+    ) -> AnyResult<()> {
+        self.sprites
+            .start_move(name, from, to, duration, easing, self.now())?;
+        self.flush();
+        Ok(())
+    }
+
+    // Erases the areas sprites have left and draws every sprite again, without moving the text cursor.
+    fn draw_sprites(&mut self, left_areas: &[(Position, usize, usize)]) {
+        let out = &mut self.pending;
+        let _ = queue!(out, cursor::SavePosition);
+        for &(position, columns, rows) in left_areas {
+            let blank = " ".repeat(columns);
+            for row in 0..rows {
+                print_at(out, position, row, &blank);
+            }
+        }
+        for (position, sprite) in self.sprites.frame() {
+            for (row, line) in sprite.lines.iter().enumerate() {
+                print_at(out, position, row, line);
+            }
+        }
+        let _ = queue!(out, cursor::RestorePosition);
+    }
+
+    /// Writes `text` on the last row, aligned to the right, without moving the cursor.
+    pub(crate) fn print_footer(&mut self, text: &str) {
+        let (columns, rows) = self.size();
+        let footer: String = format!("{:>width$}", text, width = columns as usize)
+            .chars()
+            .take(columns as usize)
+            .collect();
+        match &mut self.compositor {
+            Some(compositor) => compositor.print_row(rows.saturating_sub(1) as usize, &footer),
+            None => {
+                let _ = queue!(
+                    self.pending,
+                    cursor::SavePosition,
+                    cursor::MoveTo(0, rows.saturating_sub(1)),
+                    style::Print(footer),
+                    cursor::RestorePosition
+                );
+            }
+        }
+        self.flush();
+    }
+
+    pub(crate) fn clear(&mut self) -> AnyResult<()> {
+        self.sprites.clear();
+        match &mut self.compositor {
+            Some(compositor) => compositor.clear(),
+            None => {
+                let _ = queue!(
+                    self.pending,
+                    terminal::Clear(terminal::ClearType::All),
+                    cursor::MoveTo(0, 0)
+                );
+            }
+        }
+        self.flush();
+        Ok(())
+    }
+
+    pub(crate) fn move_cursor(&mut self, columns: usize, rows: usize) -> AnyResult<()> {
+        self.queue_move_cursor(columns, rows);
+        self.flush();
+        Ok(())
+    }
+
+    fn queue_move_cursor(&mut self, columns: usize, rows: usize) {
+        match &mut self.compositor {
+            Some(compositor) => compositor.move_cursor(columns, rows),
+            None => {
+                let _ = queue!(self.pending, cursor::MoveTo(columns as u16, rows as u16));
+            }
+        }
+    }
+
+    pub(crate) fn move_cursor_up(&mut self, rows: usize) {
+        match &mut self.compositor {
+            Some(compositor) => compositor.move_cursor_up(rows),
+            None if rows > 0 => {
+                let _ = queue!(self.pending, cursor::MoveUp(rows as u16));
+            }
+            None => (),
+        }
+    }
+
+    pub(crate) fn set_title(&mut self, title: &str) {
+        let _ = queue!(self.pending, terminal::SetTitle(title));
+        self.write_pending();
+    }
+
+    pub(crate) fn show_cursor(&mut self, shown: bool) {
+        let _ = if shown {
+            queue!(self.pending, cursor::Show)
+        } else {
+            queue!(self.pending, cursor::Hide)
+        };
+        self.write_pending();
+    }
+
+    pub(crate) fn resize(&mut self, columns: u16, rows: u16) {
+        let _ = queue!(self.pending, terminal::SetSize(columns, rows));
+        self.write_pending();
+        if self.size.is_some() {
+            self.size = Some((columns, rows));
+        }
+        if let Some(compositor) = &mut self.compositor {
+            compositor.resize(columns as u32, rows as u32);
+        }
+    }
+}
+
+// Prints `text` at the given row of a sprite, cropping what's out of the screen on the left and top.
+fn print_at(out: &mut Vec<u8>, (column, row): Position, line_offset: usize, text: &str) {
+    let row = row + line_offset as i32;
+    if row < 0 {
+        return;
+    }
+    let text: String = text.chars().skip((-column).max(0) as usize).collect();
+    let _ = queue!(
+        out,
+        cursor::MoveTo(column.max(0) as u16, row as u16),
+        style::Print(text)
+    );
+}
@@ -0,0 +1,114 @@
+use super::*;
+
+/// What an animessage does, added up from its lines without playing it, to tell how long it lasts.
+#[derive(Default, Debug)]
+pub(crate) struct Stats {
+    pub(crate) runtime: Duration, // Delays of the --[PRINT]-- , --[WAIT]-- and animations whose length is written in their args.
+    pub(crate) steps: usize,
+    pub(crate) key_waits: Vec<usize>, // Line numbers of the --[WAIT_FOR_INPUT]-- functions without a timeout.
+    pub(crate) prompts: Vec<usize>,   // Line numbers of the --[OPEN_URL]-- functions.
+    pub(crate) sound_waits: Vec<usize>, // Line numbers of the --[WAIT_AUDIO]-- functions.
+    pub(crate) untimed_delays: Vec<usize>, // Line numbers of the delays only known while playing : variables and animations without a "duration".
+    pub(crate) loops: BTreeMap<usize, (usize, u64)>, // K: line number of a GOTO going back / V: line gone to and number of times the lines in between are played.
+}
+
+impl Stats {
+    /// Counts one more pass on the GOTO at `line_number`, going back to `goto_line_number`.
+    pub(crate) fn loop_pass(&mut self, line_number: usize, goto_line_number: usize) {
+        self.loops
+            .entry(line_number)
+            .or_insert((goto_line_number, 0))
+            .1 += 1;
+    }
+
+    /// Table of the stats, not counting in the runtime what can't be timed.
+    pub(crate) fn report(&self) -> String {
+        let untimed = self.key_waits.len()
+            + self.prompts.len()
+            + self.sound_waits.len()
+            + self.untimed_delays.len();
+        let runtime = if untimed > 0 {
+            format!(
+                "{} + {} pauses which can't be timed",
+                format_duration(self.runtime),
+                untimed
+            )
+        } else {
+            format_duration(self.runtime)
+        };
+
+        let mut table = Table::new();
+        table.add_row(Row::new(vec!["Runtime".to_string(), runtime]));
+        table.add_row(Row::new(vec!["Steps".to_string(), self.steps.to_string()]));
+        table.add_row(Row::new(vec![
+            "Keys waited for".to_string(),
+            count_with_lines(&self.key_waits),
+        ]));
+        table.add_row(Row::new(vec![
+            "URLs to open".to_string(),
+            count_with_lines(&self.prompts),
+        ]));
+        table.add_row(Row::new(vec![
+            "Sounds waited for".to_string(),
+            count_with_lines(&self.sound_waits),
+        ]));
+        table.add_row(Row::new(vec![
+            "Delays only known while playing".to_string(),
+            count_with_lines(&self.untimed_delays),
+        ]));
+        for (line_number, (goto_line_number, times)) in &self.loops {
+            table.add_row(Row::new(vec![
+                format!("Lines {} to {} (GOTO)", goto_line_number, line_number),
+                format!("played {} time(s)", times),
+            ]));
+        }
+        table.render()
+    }
+}
+
+// "3 (lines 4, 12, 20)"
+fn count_with_lines(line_numbers: &[usize]) -> String {
+    if line_numbers.is_empty() {
+        return "0".to_string();
+    }
+    let lines: Vec<String> = line_numbers
+        .iter()
+        .map(|line_number| line_number.to_string())
+        .collect();
+    let label = if lines.len() == 1 { "line" } else { "lines" };
+    format!("{} ({} {})", lines.len(), label, lines.join(", "))
+}
+
+// "1m 05.250s"
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    let minutes = (seconds / 60.0).floor();
+    if minutes > 0.0 {
+        format!("{}m {:06.3}s", minutes, seconds - minutes * 60.0)
+    } else {
+        format!("{:.3}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_add_up_delays_steps_pauses_and_loops() {
+        let mut stage = Stage::new().with_headless(true);
+        let mut audio = Audio::new(false);
+        let animessage = "Hello\n--[PRINT]-- \"0.1\"\n--[WAIT]-- \"2\"\n--[WAIT_FOR_INPUT]-- \"Space\"\n--[GOTO]-- \"2\"\n--[WAIT_AUDIO]-- \"music\"\n--[WAIT]-- \"$intro\"\n--[IMAGE]-- \"logo.gif\" \"loops=0\"";
+        display_animessage(animessage, true, false, true, 0, &mut stage, &mut audio).unwrap();
+
+        let stats = stage.stats();
+        assert_eq!(stats.runtime, Duration::from_millis(4500)); // "Hello" is typed in 0.5s, then the wait is played twice.
+        assert_eq!(stage.now(), Duration::ZERO);
+        assert_eq!(stats.steps, 1); // The buffer is empty the second time.
+        assert_eq!(stats.key_waits, [4, 4]);
+        assert_eq!(stats.sound_waits, [6]);
+        assert_eq!(stats.untimed_delays, [7, 8]);
+        assert_eq!(stats.loops.get(&5), Some(&(2, 2)));
+        assert_eq!(format_duration(Duration::from_millis(65_250)), "1m 05.250s");
+    }
+}
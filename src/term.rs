@@ -1,14 +1,5 @@
 use super::*;
 
-pub(crate) fn clear_terminal(stdout: &Term) -> anyhow::Result<()> {
-    match stdout.clear_screen() {
-        Ok(_exec_ref) => Ok(()),
-        Err(_err) => {
-            anyhow::bail!("Can't clear lines in this terminal. Animessage can't work properly.")
-        }
-    }
-}
-
 // pub(crate) fn save_cursor_position(stdout: &Term) -> anyhow::Result<()> {
 //     match stdout().execute(cursor::SavePosition) {
 //         Ok(_exec_ref) => Ok(()),
@@ -23,18 +14,6 @@ pub(crate) fn clear_terminal(stdout: &Term) -> anyhow::Result<()> {
 //     }
 // }
 
-pub(crate) fn move_cursor(stdout: &Term, columns: usize, rows: usize) -> anyhow::Result<()> {
-    match stdout.move_cursor_to(columns, rows) {
-        Ok(_exec_ref) => {
-            flush_stdout();
-            Ok(())
-        }
-        Err(_err) => anyhow::bail!(
-            "Can't move the cursor in this terminal. Use another terminal such as Alacritty."
-        ),
-    }
-}
-
 pub(crate) fn move_to_previous_line(stdout: &Term, lines_n: usize) -> anyhow::Result<()> {
     match stdout.move_cursor_up(lines_n) {
         Ok(_exec_ref) => Ok(()),
@@ -82,4 +61,103 @@ pub(crate) struct Opts {
     /// Prints a summary of all the markers in the animessage showing their name and line number. Will have no effect if no marker has been found.
     #[structopt(short, long)]
     pub(crate) summary: bool,
+
+    /// Draws the animessage into an in-memory screen and only redraws the characters that changed, to avoid flickering when elements overlap or get overwritten.
+    #[structopt(short, long)]
+    pub(crate) compositor: bool,
+
+    /// Doesn't play any sound, and doesn't open the audio output device.
+    #[structopt(long)]
+    pub(crate) no_audio: bool,
+
+    /// Reads every printed step aloud with the text-to-speech program of your system, such as espeak-ng.
+    #[structopt(long)]
+    pub(crate) speak: bool,
+
+    /// Records the animessage to an asciinema file (.cast), which can be played with "asciinema play".
+    #[structopt(long)]
+    pub(crate) record: Option<PathBuf>,
+
+    /// Writes the text on screen at the end of each step to a text file, without colors. Useful to check or diff what an animessage shows.
+    #[structopt(long)]
+    pub(crate) transcript: Option<PathBuf>,
+
+    /// Plays the animessage faster or slower : every delay, print interval and frame rate is divided by this number. Sounds keep their speed.
+    #[structopt(long, parse(try_from_str = speed_from_arg), conflicts_with = "instant")]
+    pub(crate) speed: Option<f64>,
+
+    /// Skips every delay, showing the final output of each step right away. Keys are still waited for, but not sounds, which aren't played.
+    #[structopt(long)]
+    pub(crate) instant: bool,
+
+    /// Reads the keys of --[WAIT_FOR_INPUT]-- from the whole keyboard, as older versions did : they're seen even when the terminal isn't focused,
+    /// but X11 is needed on Linux and it doesn't work over SSH. By default, they're read from the terminal.
+    #[structopt(long)]
+    pub(crate) global_keys: bool,
+
+    /// Disables the playback keys : Space to pause, Enter to finish the current step, Left to go back to the previous one, Right to go forward again, N to skip to the next marker and Q to quit. Keys bound to markers by --[BIND]-- still work.
+    #[structopt(long)]
+    pub(crate) no_controls: bool,
+
+    /// Plays the animessage as slides for a talk : each --[CLEAR]-- or --[SLIDE]-- waits for Right, Space or Enter to show the next slide.
+    /// Left goes back to the previous slide, G then a number and Enter to any slide, and Q quits.
+    #[structopt(long)]
+    pub(crate) present: bool,
+
+    /// Hides the footer shown while presenting, with the slide number and the name of the current marker.
+    #[structopt(long, requires = "present")]
+    pub(crate) no_footer: bool,
+
+    /// Doesn't write to the terminal, and simulates delays instead of waiting. Sounds and keys aren't waited for. Use it with --record to record an animessage in a few milliseconds.
+    #[structopt(long)]
+    pub(crate) headless: bool,
+
+    #[structopt(subcommand)]
+    pub(crate) command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) enum Command {
+    /// Plays the animessage headlessly and converts what it draws into other formats, for example "animessage -f hello.anim export --html hello.html --gif hello.gif".
+    Export(ExportOpts),
+
+    /// Shows how long the animessage lasts, added up from its lines without playing it, its number of steps, the pauses which can't be timed and how many times its loops run.
+    Stats(StatsOpts),
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct StatsOpts {
+    /// Path to the animessage, if not given with --file.
+    pub(crate) file: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ExportOpts {
+    /// Writes a single HTML file replaying the animessage in a web browser, with play and pause buttons.
+    #[structopt(long)]
+    pub(crate) html: Option<PathBuf>,
+
+    /// Writes an SVG image animated with CSS, which can be shown in a README.
+    #[structopt(long)]
+    pub(crate) svg: Option<PathBuf>,
+
+    /// Writes an animated GIF image.
+    #[structopt(long)]
+    pub(crate) gif: Option<PathBuf>,
+
+    /// Number of columns of the exported terminal.
+    #[structopt(long, default_value = "80")]
+    pub(crate) columns: u16,
+
+    /// Number of rows of the exported terminal.
+    #[structopt(long, default_value = "24")]
+    pub(crate) rows: u16,
+
+    /// Default colors of the exported terminal : "dark" or "light".
+    #[structopt(long, default_value = "dark")]
+    pub(crate) theme: Theme,
+
+    /// Frames per second of the SVG and GIF images.
+    #[structopt(long, default_value = "10")]
+    pub(crate) fps: u32,
 }
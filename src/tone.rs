@@ -0,0 +1,210 @@
+use std::f32::consts::PI;
+
+use rodio::Source;
+
+use super::*;
+
+const SAMPLE_RATE: u32 = 44_100;
+const AMPLITUDE: f32 = 0.25; // Synthesized waves are much louder than most audio files at the same volume.
+const FADE: f32 = 0.005; // Seconds of fade in and out, to avoid clicks between notes.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Wave {
+    Sine,
+    Square,
+}
+
+impl FromStr for Wave {
+    type Err = anyhow::Error;
+
+    fn from_str(wave: &str) -> AnyResult<Self> {
+        match wave {
+            "sine" => Ok(Wave::Sine),
+            "square" => Ok(Wave::Square),
+            _ => anyhow::bail!(
+                "Unknown wave {:?}. Waves are \"sine\" and \"square\".",
+                wave
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Tone {
+    pub(crate) frequency: Option<f32>, // `None` is a rest.
+    pub(crate) duration: Duration,
+}
+
+/// Frequency in Hz, from a number of Hz ("440") or a note ("A4", "C#5", "Eb3"). The octave is 4 if omitted.
+pub(crate) fn frequency_from_arg(frequency: &str) -> AnyResult<f32> {
+    if let Ok(hz) = frequency.parse::<f32>() {
+        if hz > 0.0 && hz.is_finite() {
+            return Ok(hz);
+        }
+    }
+    note_frequency(frequency).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Can't convert {:?} into a frequency. Write a number of Hz such as \"440\", or a note such as \"A4\", \"C#5\" or \"Eb3\".",
+            frequency
+        )
+    })
+}
+
+fn note_frequency(note: &str) -> Option<f32> {
+    let mut chars = note.chars();
+    let mut semitone: i32 = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let mut rest = chars.as_str();
+    if let Some(r) = rest.strip_prefix('#') {
+        semitone += 1;
+        rest = r;
+    } else if let Some(r) = rest.strip_prefix('b') {
+        semitone -= 1;
+        rest = r;
+    }
+    let octave: i32 = if rest.is_empty() {
+        4
+    } else {
+        rest.parse().ok()?
+    };
+    let midi_note = 12 * (octave + 1) + semitone;
+    Some(440.0 * 2f32.powf((midi_note - 69) as f32 / 12.0))
+}
+
+/// Parses notes separated by spaces, written as "note:duration". "R" is a rest. Example : "C4:200ms E4:200ms R:100ms G4:0.4".
+pub(crate) fn parse_melody(melody: &str) -> AnyResult<Vec<Tone>> {
+    let tones = melody
+        .split_whitespace()
+        .map(|note| {
+            let (pitch, duration) = note.split_once(':').ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Can't read the note {:?}. Write notes as \"note:duration\", for example \"C4:200ms\".",
+                    note
+                )
+            })?;
+            let frequency = match pitch {
+                "R" | "r" => None,
+                pitch => Some(frequency_from_arg(pitch)?),
+            };
+            Ok(Tone {
+                frequency,
+                duration: duration_from_arg(duration)?,
+            })
+        })
+        .collect::<AnyResult<Vec<Tone>>>()?;
+    if tones.is_empty() {
+        anyhow::bail!("This melody has no notes.");
+    }
+    Ok(tones)
+}
+
+/// Audio source playing tones one after the other.
+pub(crate) struct Tones {
+    tones: Vec<Tone>,
+    wave: Wave,
+    looped: bool,
+    index: usize,
+    sample: u32, // Index of the next sample in the current tone.
+}
+
+impl Tones {
+    pub(crate) fn new(tones: Vec<Tone>, wave: Wave, looped: bool) -> Self {
+        let looped = looped && tones.iter().any(|tone| Tones::samples_n(tone) > 0); // Would never yield a sample otherwise.
+        Tones {
+            tones,
+            wave,
+            looped,
+            index: 0,
+            sample: 0,
+        }
+    }
+
+    fn samples_n(tone: &Tone) -> u32 {
+        (tone.duration.as_secs_f32() * SAMPLE_RATE as f32) as u32
+    }
+}
+
+impl Iterator for Tones {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        loop {
+            let tone = self.tones.get(self.index)?;
+            let samples_n = Tones::samples_n(tone);
+            if self.sample < samples_n {
+                let t = self.sample as f32 / SAMPLE_RATE as f32;
+                let remaining = (samples_n - self.sample) as f32 / SAMPLE_RATE as f32;
+                self.sample += 1;
+
+                let frequency = match tone.frequency {
+                    Some(frequency) => frequency,
+                    None => return Some(0.0),
+                };
+                let value = match self.wave {
+                    Wave::Sine => (2.0 * PI * frequency * t).sin(),
+                    Wave::Square if (frequency * t).fract() < 0.5 => 1.0,
+                    Wave::Square => -1.0,
+                };
+                let fade = (t.min(remaining) / FADE).min(1.0);
+                return Some(value * fade * AMPLITUDE);
+            }
+
+            self.sample = 0;
+            self.index += 1;
+            if self.index == self.tones.len() && self.looped {
+                self.index = 0;
+            }
+        }
+    }
+}
+
+impl Source for Tones {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        if self.looped {
+            None
+        } else {
+            Some(self.tones.iter().map(|tone| tone.duration).sum())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_and_melodies() {
+        assert_eq!(frequency_from_arg("A4").unwrap(), 440.0);
+        assert!((frequency_from_arg("C5").unwrap() - 523.25).abs() < 0.01);
+        assert_eq!(
+            frequency_from_arg("A#").unwrap(),
+            frequency_from_arg("Bb4").unwrap()
+        );
+        assert!(frequency_from_arg("H2").is_err());
+
+        let melody = parse_melody("C4:200ms R:0.1").unwrap();
+        assert_eq!(melody[1].frequency, None);
+        let samples = Tones::new(melody, Wave::Square, false).count();
+        assert_eq!(samples, 13_230); // 0.3 s at 44100 Hz.
+    }
+}
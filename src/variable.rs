@@ -1,5 +1,6 @@
 use super::*;
 
+#[derive(Clone)]
 pub enum Variable {
     String(String),
     Int(i64),
@@ -107,3 +108,53 @@ impl Variable {
         ret
     }
 }
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Variable::String(s) => write!(f, "{}", s),
+            Variable::Int(i) => write!(f, "{}", i),
+            Variable::Float(float) => write!(f, "{}", float),
+            Variable::Bool(b) => write!(f, "{}", b),
+            Variable::List(list) => {
+                let items: Vec<String> = list.iter().map(|var| var.to_string()).collect();
+                write!(f, "{}", items.join(" "))
+            }
+        }
+    }
+}
+
+/// Replaces every "$name" in `line` with the value of the variable `name`. Unknown variables are left as they are.
+pub fn substitute_vars(line: &str, vars: &HashMap<String, Variable>) -> String {
+    let mut substituted = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(dollar_index) = rest.find('$') {
+        substituted.push_str(&rest[..dollar_index]);
+        let after_dollar = &rest[dollar_index + 1..];
+        let name_len = after_dollar
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(after_dollar.len());
+        match vars.get(&after_dollar[..name_len]) {
+            Some(var) => substituted.push_str(&var.to_string()),
+            None => substituted.push_str(&rest[dollar_index..dollar_index + 1 + name_len]),
+        }
+        rest = &after_dollar[name_len..];
+    }
+    substituted.push_str(rest);
+    substituted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vars_are_substituted() {
+        let mut vars = HashMap::new();
+        vars.insert("intro_len".to_string(), Variable::Float(2.5));
+        assert_eq!(
+            substitute_vars(r#"--[WAIT]-- "$intro_len" "$unknown" "5$""#, &vars),
+            r#"--[WAIT]-- "2.5" "$unknown" "5$""#
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder},
+    AnimationDecoder, DynamicImage, GenericImageView, ImageFormat, RgbaImage,
+};
+
+use super::*;
+
+const DEFAULT_FPS: f64 = 24.0; // For directories of frames, which have no timing of their own.
+const SOUNDTRACK_CHANNEL: &str = "\u{0}video"; // Can't be the name of a channel of the animessage, which can't hold a NUL character.
+
+pub(crate) struct VideoOptions {
+    pub(crate) width: Option<u32>,
+    pub(crate) height: Option<u32>,
+    pub(crate) position: Option<Position>,
+    pub(crate) mode: ImageMode,
+    pub(crate) fps: Option<f64>, // Overrides the frame delays of GIFs and APNGs.
+    pub(crate) audio: Option<PathBuf>,
+}
+
+enum VideoFrame {
+    File(PathBuf), // Only loaded if the frame isn't dropped.
+    Decoded(RgbaImage),
+}
+
+impl VideoFrame {
+    fn load(self) -> AnyResult<DynamicImage> {
+        match self {
+            VideoFrame::File(path) => Ok(image::open(path)?),
+            VideoFrame::Decoded(buffer) => Ok(DynamicImage::ImageRgba8(buffer)),
+        }
+    }
+}
+
+type VideoFrames = Box<dyn Iterator<Item = AnyResult<(VideoFrame, Duration)>>>;
+
+/// Plays a directory of numbered images, an animated PNG or a GIF as text. Frames are dropped when rendering can't keep up, so the video stays in time with its audio track.
+pub(crate) fn play_video(
+    stage: &mut Stage,
+    path: &Path,
+    options: &VideoOptions,
+    audio: &mut Audio,
+) -> AnyResult<()> {
+    let frame_delay = options.fps.map(|fps| Duration::from_secs_f64(1.0 / fps));
+    let frames = open_frames(path, frame_delay)?;
+
+    if let Some(audio_path) = &options.audio {
+        let audio_options = AudioOptions {
+            channel: Some(SOUNDTRACK_CHANNEL.to_string()),
+            ..AudioOptions::default()
+        };
+        audio.backend().play(audio_path, &audio_options)?;
+    }
+    let result = show_frames(stage, frames, options);
+    if options.audio.is_some() {
+        let _ = audio.backend().stop(Some(SOUNDTRACK_CHANNEL)); // Whether the video has ended, been interrupted or failed. Fails if the sound has been stopped already.
+    }
+    result
+}
+
+fn show_frames(stage: &mut Stage, frames: VideoFrames, options: &VideoOptions) -> AnyResult<()> {
+    let mode = text_image_mode(stage, options.mode);
+    let mut frames = frames.peekable();
+
+    let mut size = None;
+    let mut dropped_frames_n = 0;
+    let mut shown_rows = None;
+
+    let start = stage.now();
+    let mut next_frame_time = Duration::ZERO;
+
+    while let Some(frame) = frames.next() {
+        let (frame, delay) = frame?;
+        next_frame_time += delay;
+
+        let is_late = stage.now() - start >= next_frame_time;
+        if is_late && frames.peek().is_some() {
+            dropped_frames_n += 1;
+            continue;
+        }
+
+        let image = frame.load()?;
+        let (columns, rows) = *size.get_or_insert_with(|| {
+            cells_size(
+                image.dimensions(),
+                options.width,
+                options.height,
+                max_columns(stage),
+            )
+        });
+        let cells = render(&image, columns, rows, mode);
+        if let (Some(rows), None) = (shown_rows, options.position) {
+            stage.move_cursor_up(rows); // Back to where the previous frame started.
+        }
+        stage.print_cells(&cells, options.position);
+        shown_rows = Some(cells.len());
+
+        stage.sleep(next_frame_time.saturating_sub(stage.now() - start));
+        if stage.is_interrupted() {
+            break;
+        }
+    }
+
+    if dropped_frames_n > 0 {
+        debug!(
+            "{} frames have been dropped because they couldn't be rendered in time.",
+            dropped_frames_n
+        );
+    }
+    Ok(())
+}
+
+/// Length of a directory of numbered images, read without opening them. That of GIFs and animated PNGs is only known once decoded.
+pub(crate) fn frames_duration(path: &Path, fps: Option<f64>) -> Option<Duration> {
+    if !path.is_dir() {
+        return None;
+    }
+    let frames = numbered_frames(path).ok()?;
+    Some(Duration::from_secs_f64(
+        frames.len() as f64 / fps.unwrap_or(DEFAULT_FPS),
+    ))
+}
+
+fn open_frames(path: &Path, frame_delay: Option<Duration>) -> AnyResult<VideoFrames> {
+    if path.is_dir() {
+        let delay = frame_delay.unwrap_or_else(|| Duration::from_secs_f64(1.0 / DEFAULT_FPS));
+        let frames = numbered_frames(path)?
+            .into_iter()
+            .map(move |frame_path| Ok((VideoFrame::File(frame_path), delay)));
+        return Ok(Box::new(frames));
+    }
+
+    let reader = BufReader::new(File::open(path)?);
+    let animation_frames = match ImageFormat::from_path(path)? {
+        ImageFormat::Gif => GifDecoder::new(reader)?.into_frames(),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(reader)?;
+            if !decoder.is_apng() {
+                anyhow::bail!("This PNG file isn't animated. Use --[IMAGE]-- to show it.");
+            }
+            decoder.apng().into_frames()
+        }
+        _ => anyhow::bail!(
+            "Videos must be a directory of numbered images, an animated PNG or a GIF."
+        ),
+    };
+    // Decoded one at a time, so that long videos don't have to fit in memory.
+    let frames = animation_frames.map(move |frame| {
+        let frame = frame?;
+        let delay = frame_delay.unwrap_or_else(|| image_render::frame_delay(&frame));
+        Ok((VideoFrame::Decoded(frame.into_buffer()), delay))
+    });
+    Ok(Box::new(frames))
+}
+
+/// Images of a directory sorted by the last number in their name, so that "frame_2.png" comes before "frame_10.png".
+fn numbered_frames(dir: &Path) -> AnyResult<Vec<PathBuf>> {
+    let mut frames: Vec<(u64, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file() && ImageFormat::from_path(path).is_ok())
+        .map(|path| (frame_number(&path), path))
+        .collect();
+    if frames.is_empty() {
+        anyhow::bail!("There are no images in the directory {:?}.", dir);
+    }
+    frames.sort();
+    Ok(frames.into_iter().map(|(_number, path)| path).collect())
+}
+
+fn frame_number(path: &Path) -> u64 {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let digits: String = stem
+        .chars()
+        .rev()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits
+        .chars()
+        .rev()
+        .collect::<String>()
+        .parse()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_sorted_by_number() {
+        let mut paths = vec![
+            PathBuf::from("frame_10.png"),
+            PathBuf::from("frame_2.png"),
+            PathBuf::from("frame_1.png"),
+        ];
+        paths.sort_by_key(|path| frame_number(path));
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("frame_1.png"),
+                PathBuf::from("frame_2.png"),
+                PathBuf::from("frame_10.png")
+            ]
+        );
+    }
+}
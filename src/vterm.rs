@@ -0,0 +1,289 @@
+use super::*;
+
+const TAB_WIDTH: usize = 8;
+
+#[derive(Clone, Copy, PartialEq)]
+enum EscapeState {
+    Text,
+    Escape,    // After ESC.
+    Csi,       // After ESC [, reading parameters.
+    Osc,       // After ESC ], reading until BEL or ESC \.
+    OscEscape, // ESC inside an OSC.
+    Charset,   // After ESC ( or ESC ), one more character to ignore.
+}
+
+/// In-memory terminal which interprets what Animessage writes, so its output can be turned into other formats or compared in tests.
+/// Only the escape codes Animessage uses are supported : cursor moves, clearing, colors, title, cursor visibility and resizing.
+pub(crate) struct VirtualTerminal {
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<Cell>>,
+    cursor: (usize, usize), // (column, row)
+    saved_cursor: (usize, usize),
+    pen: (CellColor, CellColor),
+    title: String,
+    cursor_visible: bool,
+    state: EscapeState,
+    params: String,
+}
+
+impl VirtualTerminal {
+    pub(crate) fn new(columns: u16, rows: u16) -> Self {
+        let (columns, rows) = (columns.max(1) as usize, rows.max(1) as usize);
+        VirtualTerminal {
+            columns,
+            rows,
+            cells: vec![vec![Cell::blank(); columns]; rows],
+            cursor: (0, 0),
+            saved_cursor: (0, 0),
+            pen: (CellColor::Default, CellColor::Default),
+            title: String::new(),
+            cursor_visible: true,
+            state: EscapeState::Text,
+            params: String::new(),
+        }
+    }
+
+    pub(crate) fn size(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    pub(crate) fn rows(&self) -> &[Vec<Cell>] {
+        &self.cells
+    }
+
+    pub(crate) fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub(crate) fn cursor(&self) -> Option<(usize, usize)> {
+        self.cursor_visible.then_some(self.cursor)
+    }
+
+    /// The screen as plain text, without trailing spaces.
+    pub(crate) fn text(&self) -> String {
+        let lines: Vec<String> = self
+            .cells
+            .iter()
+            .map(|row| {
+                let line: String = row.iter().map(|cell| cell.chr).collect();
+                line.trim_end().to_string()
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    pub(crate) fn feed(&mut self, output: &str) {
+        for c in output.chars() {
+            match self.state {
+                EscapeState::Text => self.put(c),
+                EscapeState::Escape => self.escape(c),
+                EscapeState::Csi => {
+                    if ('\u{40}'..='\u{7e}').contains(&c) {
+                        self.csi(c);
+                        self.state = EscapeState::Text;
+                    } else {
+                        self.params.push(c);
+                    }
+                }
+                EscapeState::Osc => match c {
+                    '\u{7}' => self.osc(),
+                    '\u{1b}' => self.state = EscapeState::OscEscape,
+                    c => self.params.push(c),
+                },
+                EscapeState::OscEscape => self.osc(), // ESC \ ends the OSC.
+                EscapeState::Charset => self.state = EscapeState::Text,
+            }
+        }
+    }
+
+    fn put(&mut self, c: char) {
+        match c {
+            '\u{1b}' => self.state = EscapeState::Escape,
+            '\n' => {
+                // Like a terminal in cooked mode, which turns LF into CR LF.
+                self.cursor.0 = 0;
+                self.line_feed();
+            }
+            '\r' => self.cursor.0 = 0,
+            '\t' => {
+                self.cursor.0 = ((self.cursor.0 / TAB_WIDTH + 1) * TAB_WIDTH).min(self.columns - 1)
+            }
+            '\u{8}' => self.cursor.0 = self.cursor.0.saturating_sub(1),
+            c if c.is_control() => (),
+            c => {
+                if self.cursor.0 >= self.columns {
+                    self.cursor.0 = 0;
+                    self.line_feed();
+                }
+                let (column, row) = self.cursor;
+                self.cells[row][column] = Cell {
+                    chr: c,
+                    fg: self.pen.0,
+                    bg: self.pen.1,
+                };
+                self.cursor.0 += 1; // Can be `columns` : the next character wraps.
+            }
+        }
+    }
+
+    fn line_feed(&mut self) {
+        if self.cursor.1 + 1 < self.rows {
+            self.cursor.1 += 1;
+        } else {
+            self.cells.remove(0);
+            self.cells.push(vec![Cell::blank(); self.columns]);
+        }
+    }
+
+    fn escape(&mut self, c: char) {
+        self.params.clear();
+        self.state = match c {
+            '[' => EscapeState::Csi,
+            ']' => EscapeState::Osc,
+            '(' | ')' => EscapeState::Charset,
+            '7' => {
+                self.saved_cursor = self.cursor;
+                EscapeState::Text
+            }
+            '8' => {
+                self.cursor = self.saved_cursor;
+                EscapeState::Text
+            }
+            _ => EscapeState::Text,
+        };
+    }
+
+    fn osc(&mut self) {
+        // "0;title" and "2;title" set the title.
+        if let Some(("0" | "2", title)) = self.params.split_once(';') {
+            self.title = title.to_string();
+        }
+        self.state = EscapeState::Text;
+    }
+
+    fn csi(&mut self, command: char) {
+        let private = self.params.starts_with('?');
+        let params: Vec<usize> = self
+            .params
+            .trim_start_matches('?')
+            .split(';')
+            .map(|param| param.parse().unwrap_or(0))
+            .collect();
+        let param = |index: usize, default: usize| match params.get(index) {
+            Some(&0) | None => default,
+            Some(&n) => n,
+        };
+        let (column, row) = self.cursor;
+        let last_column = self.columns - 1;
+        let last_row = self.rows - 1;
+
+        match command {
+            'A' => self.cursor.1 = row.saturating_sub(param(0, 1)),
+            'B' => self.cursor.1 = (row + param(0, 1)).min(last_row),
+            'C' => self.cursor.0 = (column + param(0, 1)).min(last_column),
+            'D' => self.cursor.0 = column.min(last_column).saturating_sub(param(0, 1)),
+            'E' => self.cursor = (0, (row + param(0, 1)).min(last_row)),
+            'F' => self.cursor = (0, row.saturating_sub(param(0, 1))),
+            'G' => self.cursor.0 = (param(0, 1) - 1).min(last_column),
+            'H' | 'f' => {
+                self.cursor = (
+                    (param(1, 1) - 1).min(last_column),
+                    (param(0, 1) - 1).min(last_row),
+                )
+            }
+            'J' => match params[0] {
+                0 => {
+                    self.clear_line_from(column, row);
+                    self.cells[row + 1..].fill(vec![Cell::blank(); self.columns]);
+                }
+                1 => {
+                    self.cells[..row].fill(vec![Cell::blank(); self.columns]);
+                    self.cells[row][..=column.min(last_column)].fill(Cell::blank());
+                }
+                _ => self.cells.fill(vec![Cell::blank(); self.columns]),
+            },
+            'K' => match params[0] {
+                0 => self.clear_line_from(column, row),
+                1 => self.cells[row][..=column.min(last_column)].fill(Cell::blank()),
+                _ => self.cells[row].fill(Cell::blank()),
+            },
+            'm' => self.sgr(&params),
+            's' => self.saved_cursor = self.cursor,
+            'u' => self.cursor = self.saved_cursor,
+            'h' | 'l' if private && params[0] == 25 => self.cursor_visible = command == 'h',
+            't' if params[0] == 8 => self.resize(param(2, self.columns), param(1, self.rows)),
+            _ => (),
+        }
+    }
+
+    fn clear_line_from(&mut self, column: usize, row: usize) {
+        if column < self.columns {
+            self.cells[row][column..].fill(Cell::blank());
+        }
+    }
+
+    fn sgr(&mut self, params: &[usize]) {
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            match param {
+                0 => self.pen = (CellColor::Default, CellColor::Default),
+                30..=37 => self.pen.0 = CellColor::Indexed((param - 30) as u8),
+                90..=97 => self.pen.0 = CellColor::Indexed((param - 90 + 8) as u8),
+                39 => self.pen.0 = CellColor::Default,
+                40..=47 => self.pen.1 = CellColor::Indexed((param - 40) as u8),
+                100..=107 => self.pen.1 = CellColor::Indexed((param - 100 + 8) as u8),
+                49 => self.pen.1 = CellColor::Default,
+                38 | 48 => {
+                    let color = match params.next() {
+                        Some(5) => params.next().map(|n| CellColor::Indexed(n as u8)),
+                        Some(2) => {
+                            let mut channel = || params.next().unwrap_or(0) as u8;
+                            Some(CellColor::Rgb(channel(), channel(), channel()))
+                        }
+                        _ => None,
+                    };
+                    if let Some(color) = color {
+                        if param == 38 {
+                            self.pen.0 = color;
+                        } else {
+                            self.pen.1 = color;
+                        }
+                    }
+                }
+                _ => (), // Bold, underline... aren't used by Animessage.
+            }
+        }
+    }
+
+    fn resize(&mut self, columns: usize, rows: usize) {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        for row in self.cells.iter_mut() {
+            row.resize(columns, Cell::blank());
+        }
+        self.cells.resize(rows, vec![Cell::blank(); columns]);
+        self.columns = columns;
+        self.rows = rows;
+        self.cursor = (self.cursor.0.min(columns - 1), self.cursor.1.min(rows - 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_codes_are_interpreted() {
+        let mut vterm = VirtualTerminal::new(10, 3);
+        vterm.feed("abc\ndef\u{1b}[1;2HX\u{1b}]0;Title\u{7}\u{1b}[?25l");
+        vterm.feed("\u{1b}[3;1H\u{1b}[38;2;255;0;0mR\u{1b}[0m\u{1b}[31mr");
+        assert_eq!(vterm.text(), "aXc\ndef\nRr");
+        assert_eq!(vterm.title(), "Title");
+        assert_eq!(vterm.cursor(), None);
+        assert_eq!(vterm.rows()[2][0].fg, CellColor::Rgb(255, 0, 0));
+        assert_eq!(vterm.rows()[2][1].fg, CellColor::Indexed(1));
+
+        vterm.feed("\u{1b}[2J\u{1b}[1;1Hlong line wraps\nthen\nscrolls");
+        assert_eq!(vterm.text(), "wraps\nthen\nscrolls");
+    }
+}